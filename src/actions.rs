@@ -0,0 +1,238 @@
+use ggez::input::keyboard::KeyCode;
+
+use crate::keys::key_name;
+use crate::world::Input;
+
+/// What a key does, independent of which key it is. Gameplay and menus only
+/// ever look at actions; the `Bindings` map keys onto them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Shoot,
+    Pause,
+    Confirm,
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Shoot,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+    ];
+
+    /// Name in the settings file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Jump => "jump",
+            Action::Shoot => "shoot",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Name on the controls screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Jump => "Jump",
+            Action::Shoot => "Shoot",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+        }
+    }
+
+    /// Used while playing; the others work in menus. Actions used in the same
+    /// place can't share a key.
+    pub fn in_game(self) -> bool {
+        matches!(
+            self,
+            Action::MoveLeft | Action::MoveRight | Action::Jump | Action::Shoot | Action::Pause
+        )
+    }
+
+    fn index(self) -> usize {
+        Action::ALL.iter().position(|&action| action == self).unwrap()
+    }
+}
+
+/// The player and action `key` would clash with if it were bound to `action`
+/// of `player`, given the bindings of every player.
+pub fn conflict(
+    bindings: &[Bindings],
+    player: usize,
+    action: Action,
+    key: KeyCode
+) -> Option<(usize, Action)> {
+    bindings.iter().enumerate().find_map(|(other_player, other_bindings)| {
+        other_bindings
+            .actions_for(key)
+            .find(|&other| {
+                (other_player, other) != (player, action) && other.in_game() == action.in_game()
+            })
+            .map(|other| (other_player, other))
+    })
+}
+
+/// A step through a menu, from the arrow keys, the d-pad or a stick. Menus
+/// move on these rather than on bindable actions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn from_arrow(key: KeyCode) -> Option<Self> {
+        match key {
+            KeyCode::Up => Some(Direction::Up),
+            KeyCode::Down => Some(Direction::Down),
+            KeyCode::Left => Some(Direction::Left),
+            KeyCode::Right => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Which keys trigger which action for one player. An action can have several
+/// keys. Menus listen to the first player's keys.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bindings {
+    keys: Vec<(Action, KeyCode)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            keys: vec![
+                (Action::MoveLeft, KeyCode::Left),
+                (Action::MoveRight, KeyCode::Right),
+                (Action::Jump, KeyCode::Up),
+                (Action::Shoot, KeyCode::Space),
+                (Action::Pause, KeyCode::P),
+                (Action::Pause, KeyCode::Escape),
+                (Action::Confirm, KeyCode::Return),
+                (Action::Confirm, KeyCode::Space),
+                (Action::Back, KeyCode::Escape)
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    /// Keys of the second player, on the left of the keyboard. Menus and pausing
+    /// are left to the first player's keys.
+    pub fn second_player() -> Self {
+        Bindings {
+            keys: vec![
+                (Action::MoveLeft, KeyCode::A),
+                (Action::MoveRight, KeyCode::D),
+                (Action::Jump, KeyCode::W),
+                (Action::Shoot, KeyCode::LShift)
+            ],
+        }
+    }
+
+    /// Every binding, in the order they were made.
+    pub fn all(&self) -> &[(Action, KeyCode)] {
+        &self.keys
+    }
+
+    pub fn keys_for(&self, action: Action) -> impl Iterator<Item = KeyCode> + '_ {
+        self.keys
+            .iter()
+            .filter(move |(bound, _)| *bound == action)
+            .map(|(_, key)| *key)
+    }
+
+    /// Actions triggered by `key`, in game and in menus alike.
+    pub fn actions_for(&self, key: KeyCode) -> impl Iterator<Item = Action> + '_ {
+        self.keys
+            .iter()
+            .filter(move |(_, bound)| *bound == key)
+            .map(|(action, _)| *action)
+    }
+
+    /// Makes `key` the only key for `action`.
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.keys.retain(|(bound, _)| *bound != action);
+        self.keys.push((action, key));
+    }
+
+    /// Adds `key` as another key for `action`.
+    pub fn add(&mut self, action: Action, key: KeyCode) {
+        if !self.keys.contains(&(action, key)) {
+            self.keys.push((action, key));
+        }
+    }
+
+    /// The keys of `action` for the controls screen, like `P, Escape`.
+    pub fn describe(&self, action: Action) -> String {
+        let names: Vec<String> = self.keys_for(action).map(key_name).collect();
+        if names.is_empty() { "-".to_string() } else { names.join(", ") }
+    }
+}
+
+/// Held actions and actions pressed since the last tick, fed by key and
+/// gamepad events and read by the game once per tick.
+#[derive(Default)]
+pub struct ActionState {
+    held: [u8; Action::ALL.len()], // by how many keys and buttons at once
+    pressed: [bool; Action::ALL.len()],
+}
+
+impl ActionState {
+    pub fn set(&mut self, action: Action, down: bool) {
+        let index = action.index();
+        if down {
+            self.pressed[index] |= self.held[index] == 0;
+            self.held[index] = self.held[index].saturating_add(1);
+        } else {
+            self.held[index] = self.held[index].saturating_sub(1);
+        }
+    }
+
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held[action.index()] > 0
+    }
+
+    pub fn was_pressed(&self, action: Action) -> bool {
+        self.pressed[action.index()]
+    }
+
+    pub fn clear_presses(&mut self) {
+        self.pressed = Default::default();
+    }
+
+    /// Releases everything, e.g. when a level starts, so no key carries over.
+    pub fn clear(&mut self) {
+        *self = ActionState::default();
+    }
+
+    /// The simulation input for the coming tick.
+    pub fn gameplay_input(&self) -> Input {
+        Input {
+            left: self.is_held(Action::MoveLeft),
+            right: self.is_held(Action::MoveRight),
+            jump: self.was_pressed(Action::Jump),
+            shoot: self.was_pressed(Action::Shoot),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::assets::AssetManifest;
+
+/// Animation clips, one per line:
+///
+///     <name> <loop|once|pingpong> <seconds per frame> [<asset>...] [-> <event>]
+///
+/// The frames are the files of the listed manifest assets, or of the asset
+/// named like the clip when none are listed. A clip can name an event that its
+/// animator reports once when the clip has played through; only `once` clips
+/// ever finish. Lines starting with `;` are comments.
+pub const ANIMATIONS_PATH: &str = "resources/animations.txt";
+/// Asset drawn for clips that are missing from the file.
+pub const FALLBACK_ASSET: &str = "blank";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlayMode {
+    Loop,
+    Once,
+    PingPong,
+}
+
+#[derive(Debug)]
+pub struct Clip {
+    pub name: String,
+    pub mode: PlayMode,
+    pub frame_time: f32,
+    pub frames: Vec<String>,
+    pub on_finish: Option<String>,
+}
+
+impl Clip {
+    fn fallback(name: &str, frame: &str) -> Self {
+        Clip {
+            name: name.to_string(),
+            mode: PlayMode::Loop,
+            frame_time: 1.0,
+            frames: vec![frame.to_string()],
+            on_finish: None,
+        }
+    }
+
+    /// Length of one pass through the clip in frames.
+    fn cycle(&self) -> usize {
+        match self.mode {
+            PlayMode::PingPong if self.frames.len() > 1 => self.frames.len() * 2 - 2,
+            _ => self.frames.len(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AnimationError {
+    Io(PathBuf, std::io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnimationError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            AnimationError::Parse { path, line, message } =>
+                write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Clips {
+    clips: HashMap<String, Rc<Clip>>,
+    fallback_frame: String, // first file of `FALLBACK_ASSET`
+}
+
+impl Clips {
+    pub fn load(manifest: &AssetManifest) -> Result<Self, AnimationError> {
+        let path = PathBuf::from(ANIMATIONS_PATH);
+        let text = fs::read_to_string(&path).map_err(|err| AnimationError::Io(path.clone(), err))?;
+        Clips::parse(&text, manifest).map_err(|(line, message)| AnimationError::Parse {
+            path,
+            line,
+            message,
+        })
+    }
+
+    /// Parses the clip file, reporting problems as 1-based `(line, message)`.
+    pub fn parse(text: &str, manifest: &AssetManifest) -> Result<Self, (usize, String)> {
+        let mut clips = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let (definition, on_finish) = match line.split_once("->") {
+                Some((definition, event)) => {
+                    let event = event.trim();
+                    if event.is_empty() || event.contains(char::is_whitespace) {
+                        return Err((number, "expected one event name after '->'".to_string()));
+                    }
+                    (definition, Some(event.to_string()))
+                }
+                None => (line, None),
+            };
+            let fields: Vec<&str> = definition.split_whitespace().collect();
+            let [name, mode, frame_time, assets @ ..] = &fields[..] else {
+                return Err((
+                    number,
+                    "expected '<name> <loop|once|pingpong> <seconds> [<asset>...]'".to_string(),
+                ));
+            };
+            let mode = match *mode {
+                "loop" => PlayMode::Loop,
+                "once" => PlayMode::Once,
+                "pingpong" => PlayMode::PingPong,
+                _ => {
+                    return Err((number, format!("unknown play mode '{}'", mode)));
+                }
+            };
+            let frame_time = frame_time
+                .parse::<f32>()
+                .ok()
+                .filter(|time| *time > 0.0)
+                .ok_or((number, format!("bad frame time '{}'", frame_time)))?;
+            let assets = if assets.is_empty() { &[*name][..] } else { assets };
+            let mut frames = vec![];
+            for asset in assets {
+                let asset = manifest
+                    .get(asset)
+                    .ok_or((number, format!("no asset '{}' in the manifest", asset)))?;
+                frames.extend(asset.files.iter().cloned());
+            }
+            if clips.contains_key(*name) {
+                return Err((number, format!("clip '{}' is defined twice", name)));
+            }
+
+            let clip = Clip {
+                name: name.to_string(),
+                mode,
+                frame_time,
+                frames,
+                on_finish,
+            };
+            clips.insert(clip.name.clone(), Rc::new(clip));
+        }
+        let fallback_frame = manifest.files(FALLBACK_ASSET).first().cloned().unwrap_or_default();
+        Ok(Clips { clips, fallback_frame })
+    }
+
+    /// The clip with this name, or a single blank frame if there is none.
+    pub fn get(&self, name: &str) -> Rc<Clip> {
+        self.clips
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| Rc::new(Clip::fallback(name, &self.fallback_frame)))
+    }
+}
+
+/// Plays one clip at a time and tells which frame to draw. Only deals in file
+/// names, so it runs in the headless simulation.
+pub struct Animator {
+    clip: Rc<Clip>,
+    time: f32,
+    finished: bool,
+}
+
+impl Animator {
+    pub fn new(clip: Rc<Clip>) -> Self {
+        Animator {
+            clip,
+            time: 0.0,
+            finished: false,
+        }
+    }
+
+    /// Switches to `clip` from its first frame, unless it's already playing.
+    pub fn play(&mut self, clip: &Rc<Clip>) {
+        if !Rc::ptr_eq(&self.clip, clip) {
+            self.restart(clip);
+        }
+    }
+
+    /// Switches to `clip` from its first frame, even if it's already playing.
+    pub fn restart(&mut self, clip: &Rc<Clip>) {
+        self.clip = Rc::clone(clip);
+        self.time = 0.0;
+        self.finished = false;
+    }
+
+    /// Takes the clip of the same name from reloaded `clips`, carrying on
+    /// where the old one was.
+    pub fn reload(&mut self, clips: &Clips) {
+        self.clip = clips.get(&self.clip.name);
+    }
+
+    /// Advances the clip, returning its finish event on the update it ends.
+    pub fn update(&mut self, dt: f32) -> Option<String> {
+        if self.finished {
+            return None;
+        }
+        self.time += dt;
+        let done =
+            self.clip.mode == PlayMode::Once &&
+            self.time >= self.clip.frame_time * (self.clip.frames.len() as f32);
+        if !done {
+            return None;
+        }
+        self.finished = true;
+        self.clip.on_finish.clone()
+    }
+
+    /// Image file of the frame to draw right now.
+    pub fn frame(&self) -> &str {
+        let clip = &self.clip;
+        let step = (self.time / clip.frame_time) as usize;
+        let index = match clip.mode {
+            PlayMode::Once => step.min(clip.frames.len() - 1),
+            PlayMode::Loop => step % clip.cycle(),
+            PlayMode::PingPong => {
+                let position = step % clip.cycle();
+                if position < clip.frames.len() {
+                    position
+                } else {
+                    clip.cycle() - position
+                }
+            }
+        };
+        &clip.frames[index]
+    }
+}
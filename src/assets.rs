@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Maps logical asset names to files under `resources/`, one asset per line:
+///
+///     <name> <file>... [<key>=<value>]...
+///
+/// Names are dotted like `player.run_left` or `enemy.robot0.walk_left`; files
+/// are listed in frame order. The `key=value` pairs are free-form metadata for
+/// the code using the asset, e.g. the `score` of a `fruit.*` entry, or
+/// `critical=yes` for assets the game refuses to start without. Lines
+/// starting with `;` are comments.
+pub const MANIFEST_PATH: &str = "resources/assets.txt";
+
+#[derive(Clone, Debug)]
+pub struct Asset {
+    pub name: String,
+    pub files: Vec<String>,
+    pub meta: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(PathBuf, std::io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            AssetError::Parse { path, line, message } =>
+                write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct AssetManifest {
+    assets: Vec<Asset>, // in file order
+    by_name: HashMap<String, usize>,
+}
+
+impl AssetManifest {
+    pub fn load() -> Result<Self, AssetError> {
+        let path = PathBuf::from(MANIFEST_PATH);
+        let text = fs::read_to_string(&path).map_err(|err| AssetError::Io(path.clone(), err))?;
+        AssetManifest::parse(&text).map_err(|(line, message)| AssetError::Parse {
+            path,
+            line,
+            message,
+        })
+    }
+
+    /// Parses the manifest, reporting problems as 1-based `(line, message)`.
+    pub fn parse(text: &str) -> Result<Self, (usize, String)> {
+        let mut manifest = AssetManifest::default();
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or_default().to_string();
+            if manifest.by_name.contains_key(&name) {
+                return Err((number, format!("asset '{}' is defined twice", name)));
+            }
+            let mut asset = Asset { name, files: vec![], meta: HashMap::new() };
+            for field in fields {
+                match field.split_once('=') {
+                    Some((key, value)) => {
+                        asset.meta.insert(key.to_string(), value.to_string());
+                    }
+                    None => asset.files.push(field.to_string()),
+                }
+            }
+            if asset.files.is_empty() {
+                return Err((number, format!("asset '{}' has no files", asset.name)));
+            }
+
+            manifest.by_name.insert(asset.name.clone(), manifest.assets.len());
+            manifest.assets.push(asset);
+        }
+        Ok(manifest)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Asset> {
+        self.by_name.get(name).map(|&index| &self.assets[index])
+    }
+
+    /// The files of an asset, none if there is no such asset.
+    pub fn files(&self, name: &str) -> &[String] {
+        self.get(name).map_or(&[], |asset| &asset.files)
+    }
+
+    /// Assets named `<group>.<something>`, in manifest order.
+    pub fn group<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a Asset> {
+        self.assets.iter().filter(move |asset| {
+            asset.name.strip_prefix(group).is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// Every asset, in manifest order.
+    pub fn assets(&self) -> &[Asset] {
+        &self.assets
+    }
+}
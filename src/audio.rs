@@ -0,0 +1,215 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use rodio::{ Decoder, OutputStream, OutputStreamHandle, Sink, Source };
+
+use crate::resources::Resources;
+use crate::settings::Settings;
+use crate::world::Event;
+
+/// The bytes of a sound file, decoded anew every time it plays.
+#[derive(Clone, Debug)]
+pub struct SoundData(Arc<[u8]>);
+
+impl SoundData {
+    pub fn from_bytes(data: &[u8]) -> Self {
+        SoundData(Arc::from(data))
+    }
+
+    pub fn can_play(&self) -> bool {
+        self.decoder().is_ok()
+    }
+
+    fn decoder(&self) -> Result<Decoder<Cursor<SoundData>>, rodio::decoder::DecoderError> {
+        Decoder::new(Cursor::new(self.clone()))
+    }
+}
+
+impl AsRef<[u8]> for SoundData {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// How many copies of one sound effect play at once; the oldest copy is cut
+/// off to make room for another.
+const MAX_COPIES: usize = 4;
+pub const LEVEL_CLEAR: &str = "sfx.level_clear";
+
+/// The sound effect played for something that happened in the world.
+pub fn event_sound(event: Event) -> &'static str {
+    match event {
+        Event::Shoot => "sfx.shoot",
+        Event::Jump => "sfx.jump",
+        Event::Capture => "sfx.capture",
+        Event::Pop => "sfx.pop",
+        Event::Fruit => "sfx.fruit",
+        Event::Death => "sfx.death",
+    }
+}
+
+enum Backend {
+    /// Plays through the default output device.
+    Device {
+        _stream: OutputStream, // the device closes when this is dropped
+        handle: OutputStreamHandle,
+        theme: Option<SoundData>,
+        music: Option<Sink>,
+        effects: Vec<(&'static str, Sink)>, // playing or done, oldest first
+    },
+    /// Plays nothing, for machines without an output device and devices that
+    /// stopped working.
+    Null,
+}
+
+/// Music and sound effects, with separate volumes and a mute switch. Sounds
+/// are played by asset name; missing ones stay silent.
+pub struct Audio {
+    backend: Backend,
+    music_volume: f32,
+    sfx_volume: f32,
+    muted: bool,
+}
+
+impl Audio {
+    pub fn new(resources: &Resources, settings: &Settings) -> Self {
+        Audio {
+            backend: open(resources),
+            music_volume: settings.music_volume,
+            sfx_volume: settings.sfx_volume,
+            muted: settings.muted,
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply_volumes();
+    }
+
+    pub fn set_volumes(&mut self, music: f32, sfx: f32) {
+        self.music_volume = music;
+        self.sfx_volume = sfx;
+        self.apply_volumes();
+    }
+
+    /// Holds the music and every sound effect where they are, e.g. while the
+    /// game is paused.
+    pub fn pause(&self) {
+        if let Backend::Device { music, effects, .. } = &self.backend {
+            music.iter().for_each(Sink::pause);
+            effects.iter().for_each(|(_, sink)| sink.pause());
+        }
+    }
+
+    /// Carries on with whatever `pause` held.
+    pub fn resume(&self) {
+        if let Backend::Device { music, effects, .. } = &self.backend {
+            music.iter().for_each(Sink::play);
+            effects.iter().for_each(|(_, sink)| sink.play());
+        }
+    }
+
+    /// Starts the music over from the beginning.
+    pub fn play_music(&mut self) {
+        let volume = self.music_volume();
+        let Backend::Device { handle, theme: Some(theme), music, .. } = &mut self.backend else {
+            return;
+        };
+        *music = None; // dropping a sink stops it
+        match theme.decoder() {
+            Ok(decoder) =>
+                match start(handle, decoder.repeat_infinite(), volume) {
+                    Some(sink) => {
+                        *music = Some(sink);
+                    }
+                    None => {
+                        self.backend = Backend::Null;
+                    }
+                }
+            Err(err) => eprintln!("Could not play music: {}", err),
+        }
+    }
+
+    /// Plays the sound effect `name` on top of whatever is playing.
+    pub fn play(&mut self, resources: &Resources, name: &'static str) {
+        let volume = self.effect_volume();
+        let Backend::Device { handle, effects, .. } = &mut self.backend else {
+            return;
+        };
+        let Some(Ok(decoder)) = resources.sound(name).map(SoundData::decoder) else {
+            return;
+        };
+        effects.retain(|(_, sink)| !sink.empty());
+        let copies: Vec<usize> = effects
+            .iter()
+            .enumerate()
+            .filter(|(_, (playing, _))| *playing == name)
+            .map(|(index, _)| index)
+            .collect();
+        if copies.len() >= MAX_COPIES {
+            effects.remove(copies[0]); // dropping a sink stops it
+        }
+
+        match start(handle, decoder, volume) {
+            Some(sink) => effects.push((name, sink)),
+            None => {
+                self.backend = Backend::Null;
+            }
+        }
+    }
+
+    fn music_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.music_volume }
+    }
+
+    fn effect_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.sfx_volume }
+    }
+
+    fn apply_volumes(&mut self) {
+        let (music_volume, effect_volume) = (self.music_volume(), self.effect_volume());
+        if let Backend::Device { music, effects, .. } = &mut self.backend {
+            music.iter().for_each(|music| music.set_volume(music_volume));
+            effects.iter().for_each(|(_, sink)| sink.set_volume(effect_volume));
+        }
+    }
+}
+
+/// Opens the default output device, or goes silent when there is none.
+fn open(resources: &Resources) -> Backend {
+    match OutputStream::try_default() {
+        Ok((stream, handle)) =>
+            Backend::Device {
+                _stream: stream,
+                handle,
+                theme: resources.sound("music.theme").cloned(),
+                music: None,
+                effects: vec![],
+            },
+        Err(err) => {
+            println!("No audio output, the game is silent: {}", err);
+            Backend::Null
+        }
+    }
+}
+
+/// Plays `source` on a sink of its own, `None` once the device stopped working.
+fn start<S>(handle: &OutputStreamHandle, source: S, volume: f32) -> Option<Sink>
+    where S: Source<Item = i16> + Send + 'static
+{
+    match Sink::try_new(handle) {
+        Ok(sink) => {
+            sink.set_volume(volume);
+            sink.append(source);
+            Some(sink)
+        }
+        Err(err) => {
+            eprintln!("Audio stopped working, going on without sound: {}", err);
+            None
+        }
+    }
+}
@@ -1,52 +1,101 @@
-use ggez::graphics::DrawParam;
+use crate::{
+    animation::{ Animator, Clips },
+    camera::Camera,
+    enemy::Enemy,
+    game::Game,
+    grid::{ GRID_HEIGHT, GRID_WIDTH },
+    level::Current,
+};
 
-use crate::{ game::Game, grid::GRID_WIDTH };
+/// Seconds a trapped enemy stays in its bubble before breaking out.
+pub const BUBBLE_LIFETIME: f32 = 8.0;
+/// Seconds an empty bubble floats around before it bursts on its own.
+pub const EMPTY_BUBBLE_LIFETIME: f32 = 6.0;
+/// Radius of a bubble in tiles.
+pub const BUBBLE_RADIUS: f32 = 1.4;
+/// Drift speed along the air currents in tiles per second.
+const DRIFT_SPEED: f32 = 2.4;
 
 pub struct Bubble {
-    pos: (f32, f32),
-    velocity: (f32, f32),
-    current_frame: usize,
-    frame_timer: f32,
+    pub pos: (f32, f32), // center of the bubble
+    pub occupant: Option<Enemy>,
+    pub lifetime: f32,
+    pub animator: Animator,
+    popping: bool,
+    gone: bool, // the pop clip has played out
 }
 
 impl Bubble {
-    pub fn new(pos: (f32, f32), velocity: (f32, f32)) -> Self {
+    pub fn new(pos: (f32, f32), occupant: Option<Enemy>, clips: &Clips) -> Self {
+        // Every enemy sprite set comes with its own trapped animation
+        let clip = match &occupant {
+            Some(enemy) => enemy.clip(clips, "trapped"),
+            None => clips.get("bubble.empty"),
+        };
         Bubble {
             pos,
-            velocity,
-            current_frame: 0,
-            frame_timer: 0.0,
+            lifetime: if occupant.is_some() { BUBBLE_LIFETIME } else { EMPTY_BUBBLE_LIFETIME },
+            occupant,
+            animator: Animator::new(clip),
+            popping: false,
+            gone: false,
         }
     }
 
-    pub fn update(&mut self, ctx: &mut ggez::Context) {
-        self.pos.1 += self.velocity.1;
+    pub fn update(&mut self, dt: f32, currents: &[[Current; GRID_WIDTH]; GRID_HEIGHT]) {
+        let event = self.animator.update(dt);
+        if self.popping {
+            self.gone |= event.as_deref() == Some("gone");
+            return;
+        }
+
+        // Drift along the air current of the cell the bubble is in, but stay
+        // inside the walls and below the ceiling
+        let cell_x = (self.pos.0 as usize).min(GRID_WIDTH - 1);
+        let cell_y = (self.pos.1 as usize).min(GRID_HEIGHT - 1);
+        let direction = currents[cell_y][cell_x].direction();
+        let margin = 2.0;
+        self.pos.0 = (self.pos.0 + direction.0 * DRIFT_SPEED * dt).clamp(
+            margin + 1.0,
+            (GRID_WIDTH as f32) - margin - 1.0
+        );
+        self.pos.1 = (self.pos.1 + direction.1 * DRIFT_SPEED * dt).clamp(
+            margin,
+            (GRID_HEIGHT as f32) - margin
+        );
+        self.lifetime -= dt;
+    }
 
-        let delta_time = ctx.time.delta().as_secs_f32();
-        self.frame_timer += delta_time;
-        if self.frame_timer >= 0.1 {
-            self.current_frame = (self.current_frame + 1) % 8;
-            self.frame_timer = 0.0;
+    /// Bursts the bubble and hands out whatever was trapped inside.
+    pub fn pop(&mut self, clips: &Clips) -> Option<Enemy> {
+        if !self.popping {
+            self.popping = true;
+            self.animator.restart(&clips.get("bubble.pop"));
         }
+        self.occupant.take()
+    }
+
+    pub fn is_popping(&self) -> bool {
+        self.popping
+    }
+
+    pub fn is_expired(&self) -> bool {
+        !self.popping && self.lifetime <= 0.0
+    }
+
+    /// Whether the pop clip has played out (its `gone` event) and the bubble can go.
+    pub fn is_gone(&self) -> bool {
+        self.gone
     }
 
     pub fn draw(canvas: &mut ggez::graphics::Canvas, game: &mut Game) {
-        for bubble in &game.bubbles {
-            let image = game.resources.bobble_image[bubble.current_frame].clone();
-            for bubble in &game.bubbles {
-                canvas.draw(
-                    &image,
-                    DrawParam::default()
-                        .dest(ggez::mint::Point2 {
-                            x: bubble.pos.0,
-                            y: bubble.pos.1 - game.block_size,
-                        })
-                        .scale(ggez::mint::Vector2 {
-                            x: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285,
-                            y: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285,
-                        })
-                );
-            }
+        let world = &game.world;
+        let resources = &game.resources;
+        for bubble in &world.bubbles {
+            let image = resources.frame(bubble.animator.frame());
+            let size = Camera::tiles(image);
+            let pos = (bubble.pos.0 - size.0 / 2.0, bubble.pos.1 - size.1 / 2.0);
+            canvas.draw(image, game.camera.sprite(pos, 1.0));
         }
     }
 }
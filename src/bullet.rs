@@ -1,41 +1,54 @@
 use crate::{ game::Game, grid::{ GRID_HEIGHT, GRID_WIDTH } };
-use ggez::graphics::{ self, DrawParam };
+
+/// Tiles a shot flies at full speed before it starts to slow down.
+const SHOT_RANGE: f32 = 4.0;
+/// Fraction of its speed a slowing shot loses per second.
+const SHOT_DRAG: f32 = 8.0;
+/// Below this speed (tiles per second) a shot turns into a bubble.
+const BUBBLE_SPEED: f32 = 2.0;
 
 pub struct Bullet {
     pub pos: (f32, f32),
     pub velocity: (f32, f32),
-    pub image: graphics::Image,
+    travelled: f32,
 }
 
 impl Bullet {
-    pub fn update(&mut self) {
-        self.pos.0 += self.velocity.0;
-        self.pos.1 += self.velocity.1;
+    pub fn new(pos: (f32, f32), velocity: (f32, f32)) -> Self {
+        Bullet {
+            pos,
+            velocity,
+            travelled: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.pos.0 += self.velocity.0 * dt;
+        self.pos.1 += self.velocity.1 * dt;
+
+        self.travelled += self.velocity.0.abs() * dt;
+        if self.travelled > SHOT_RANGE {
+            self.velocity.0 *= (1.0 - SHOT_DRAG * dt).max(0.0);
+        }
+    }
+
+    /// Whether the shot has slowed down enough to become an empty bubble.
+    pub fn is_spent(&self) -> bool {
+        self.velocity.0.abs() < BUBBLE_SPEED
     }
 
-    pub fn is_off_screen(&self, block_size: f32) -> bool {
+    pub fn is_off_screen(&self) -> bool {
         self.pos.0 < 0.0 ||
-            self.pos.0 > (GRID_WIDTH as f32) * block_size ||
+            self.pos.0 > (GRID_WIDTH as f32) ||
             self.pos.1 < 0.0 ||
-            self.pos.1 > (GRID_HEIGHT as f32) * block_size
+            self.pos.1 > (GRID_HEIGHT as f32)
     }
 
     pub fn draw(canvas: &mut ggez::graphics::Canvas, game: &mut Game) {
-        for bullet in game.bullets.iter() {
-            canvas.draw(
-                &bullet.image,
-                DrawParam::default()
-                    .dest(ggez::mint::Point2 {
-                        x: bullet.pos.0,
-                        y: bullet.pos.1 - game.block_size / 2.0,
-                    })
-                    .scale(ggez::mint::Vector2 {
-                        x: (game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285) *
-                        0.5,
-                        y: (game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285) *
-                        0.5,
-                    })
-            );
+        let world = &game.world;
+        for bullet in world.bullets.iter() {
+            let pos = (bullet.pos.0 - 0.7, bullet.pos.1 - 0.7);
+            canvas.draw(game.resources.image("player.shot"), game.camera.sprite(pos, 0.5));
         }
     }
 }
@@ -0,0 +1,73 @@
+use ggez::graphics::{ DrawParam, Image, Rect };
+use ggez::mint::{ Point2, Vector2 };
+
+use crate::grid::{ GRID_HEIGHT, GRID_WIDTH };
+
+/// Pixels of sprite art per tile, the art is drawn at this size.
+pub const ART_TILE_SIZE: f32 = 25.0;
+/// Smallest window the game draws into. Anything smaller, like the 0x0 some
+/// platforms report for a minimised window, is ignored.
+pub const MIN_SCREEN: (f32, f32) = (320.0, 180.0);
+
+/// Maps the playfield's tile coordinates onto the drawable area. The only
+/// place that knows about pixels; the simulation runs in tiles throughout.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub screen: (f32, f32), // drawable size in pixels
+    pub scale: f32, // pixels per tile
+    origin: (f32, f32), // top left corner of the playfield in pixels
+}
+
+impl Camera {
+    /// Fits the whole playfield into a drawable area of `screen` pixels,
+    /// centered with black bars along the sides that are too long.
+    pub fn fit(screen: (f32, f32)) -> Self {
+        let scale = (screen.0 / (GRID_WIDTH as f32)).min(screen.1 / (GRID_HEIGHT as f32));
+        let origin = (
+            ((screen.0 - (GRID_WIDTH as f32) * scale) / 2.0).floor(),
+            ((screen.1 - (GRID_HEIGHT as f32) * scale) / 2.0).floor(),
+        );
+        Camera { screen, scale, origin }
+    }
+
+    pub fn to_screen(self, pos: (f32, f32)) -> Point2<f32> {
+        Point2 { x: self.origin.0 + pos.0 * self.scale, y: self.origin.1 + pos.1 * self.scale }
+    }
+
+    /// The playfield in pixels, to keep sprites out of the bars.
+    pub fn playfield(&self) -> Rect {
+        Rect::new(
+            self.origin.0,
+            self.origin.1,
+            (GRID_WIDTH as f32) * self.scale,
+            (GRID_HEIGHT as f32) * self.scale
+        )
+    }
+
+    /// Whether the playfield covers any pixels; an empty scissor rect is an error.
+    pub fn has_playfield(&self) -> bool {
+        let playfield = self.playfield();
+        playfield.w >= 1.0 && playfield.h >= 1.0
+    }
+
+    /// Draws sprite art with its top left corner at `pos`, `size` times as big
+    /// as the art is meant to be.
+    pub fn sprite(&self, pos: (f32, f32), size: f32) -> DrawParam {
+        let scale = (self.scale / ART_TILE_SIZE) * size;
+        DrawParam::default().dest(self.to_screen(pos)).scale(Vector2 { x: scale, y: scale })
+    }
+
+    /// Draws sprite art at `pos` pixels from the top left of the screen rather
+    /// than the playfield, scaled like the rest of the art. For the HUD.
+    pub fn overlay(&self, pos: (f32, f32), size: f32) -> DrawParam {
+        let scale = (self.scale / ART_TILE_SIZE) * size;
+        DrawParam::default()
+            .dest(Point2 { x: pos.0, y: pos.1 })
+            .scale(Vector2 { x: scale, y: scale })
+    }
+
+    /// Size of sprite art in tiles.
+    pub fn tiles(image: &Image) -> (f32, f32) {
+        ((image.width() as f32) / ART_TILE_SIZE, (image.height() as f32) / ART_TILE_SIZE)
+    }
+}
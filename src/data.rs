@@ -0,0 +1,104 @@
+use std::fmt;
+
+use crate::animation::{ AnimationError, Clips, FALLBACK_ASSET };
+use crate::assets::{ AssetError, AssetManifest };
+use crate::enemy_type::{ EnemyTypeError, EnemyTypes };
+
+/// Everything besides the levels that the simulation reads from `resources/`.
+/// Loaded as a whole so that the files always fit together.
+#[derive(Clone, Default)]
+pub struct GameData {
+    pub manifest: AssetManifest,
+    pub enemy_types: EnemyTypes,
+    pub clips: Clips,
+    /// Points per fruit, from the `score` of the `fruit.*` assets in manifest order.
+    pub fruit_scores: Vec<i32>,
+}
+
+/// Assets drawn by name rather than through a clip. Drawing can't go on
+/// without them, so the manifest must have them.
+const REQUIRED_ASSETS: [&str; 8] = [
+    FALLBACK_ASSET,
+    "player.shot",
+    "level.blocks",
+    "level.backgrounds",
+    "screen.game_over",
+    "hud.health",
+    "hud.life",
+    "hud.plus",
+];
+
+#[derive(Debug)]
+pub enum DataError {
+    Assets(AssetError),
+    MissingAsset(&'static str),
+    EnemyTypes(EnemyTypeError),
+    Animations(AnimationError),
+    Fruit(String),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Assets(err) => write!(f, "{}", err),
+            DataError::MissingAsset(name) => write!(f, "the manifest has no '{}' asset", name),
+            DataError::EnemyTypes(err) => write!(f, "{}", err),
+            DataError::Animations(err) => write!(f, "{}", err),
+            DataError::Fruit(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl GameData {
+    pub fn load() -> Result<Self, DataError> {
+        let manifest = AssetManifest::load().map_err(DataError::Assets)?;
+        check_required(&manifest)?;
+        let enemy_types = EnemyTypes::load().map_err(DataError::EnemyTypes)?;
+        let clips = Clips::load(&manifest).map_err(DataError::Animations)?;
+
+        let mut fruit_scores = vec![];
+        for fruit in manifest.group("fruit") {
+            let score = fruit.meta
+                .get("score")
+                .and_then(|score| score.parse().ok())
+                .ok_or_else(|| DataError::Fruit(format!("{} needs a score=<points>", fruit.name)))?;
+            fruit_scores.push(score);
+        }
+        if fruit_scores.is_empty() {
+            return Err(DataError::Fruit("the manifest has no fruit.* assets".to_string()));
+        }
+
+        Ok(GameData { manifest, enemy_types, clips, fruit_scores })
+    }
+}
+
+fn check_required(manifest: &AssetManifest) -> Result<(), DataError> {
+    match REQUIRED_ASSETS.into_iter().find(|name| manifest.get(name).is_none()) {
+        Some(name) => Err(DataError::MissingAsset(name)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_data_loads() {
+        let data = GameData::load().unwrap();
+        assert!(check_required(&data.manifest).is_ok());
+        assert_eq!(data.fruit_scores.len(), data.manifest.group("fruit").count());
+    }
+
+    #[test]
+    fn manifest_without_a_required_asset_is_rejected() {
+        let text = std::fs::read_to_string(crate::assets::MANIFEST_PATH).unwrap();
+        let without_hud: String = text
+            .lines()
+            .filter(|line| !line.starts_with("hud.life "))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let manifest = AssetManifest::parse(&without_hud).unwrap();
+        assert!(matches!(check_required(&manifest), Err(DataError::MissingAsset("hud.life"))));
+    }
+}
@@ -1,52 +1,240 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use std::rc::Rc;
+
+use crate::animation::{ Animator, Clip, Clips };
+use crate::enemy_type::{ EnemyType, EnemyTypes };
 use crate::grid::{ GRID_WIDTH, GRID_HEIGHT };
-use crate::utils::{ check_collision, get_y_pos_correction };
-use ggez::graphics::DrawParam;
+use crate::level::Level;
+use crate::nav::{ EdgeKind, NavGraph };
+use crate::world::Difficulty;
+use crate::utils::{ cell_to_pos, check_collision, pos_to_cell, COLLISION_MARGIN, GRAVITY };
+use ggez::graphics::Color;
 use crate::game::Game;
 
+/// Speed factor for enemies that broke out of a bubble.
+const ANGRY_SPEEDUP: f32 = 1.5;
+/// Flyers move up and down at this fraction of their speed.
+const FLY_CLIMB: f32 = 0.5;
+/// Seconds between two decisions about what to do next.
+const THINK_TIME: f32 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Behaviour {
+    Chase,
+    Patrol,
+    Wander,
+}
+
 pub struct Enemy {
     pub pos: (f32, f32), //(pos_x,pos_y)
     pub velocity: (f32, f32),
+    pub enemy_type: EnemyType,
+    pub angry: bool,
+    pub facing_right: bool,
+    pub animator: Animator,
+    firing: bool, // the fire clip is playing
+    pub behaviour: Behaviour,
+    goal: Option<(usize, usize)>, // standing cell a wanderer is heading for
+    think_timer: f32,
 }
 
-pub fn create_enemies(window_width: f32, window_height: f32, block_size: f32) -> Vec<Enemy> {
-    vec![
-        Enemy {
-            pos: (window_width / 8.0, window_height / 2.0),
-            velocity: (block_size / 15.0, block_size / 7.0),
-        },
-        Enemy {
-            pos: (window_width / 4.0, 00.0),
-            velocity: (block_size / 8.0, 0.0),
-        },
-        Enemy {
-            pos: (window_width / 4.0, 00.0),
-            velocity: (-block_size / 15.0, 0.0),
-        }
-    ]
+pub fn create_enemies(
+    level: &Level,
+    types: &EnemyTypes,
+    difficulty: Difficulty,
+    clips: &Clips
+) -> Vec<Enemy> {
+    level.enemy_spawns
+        .iter()
+        .map(|spawn| Enemy::new(
+            cell_to_pos(spawn.cell),
+            types.get(spawn.kind).for_difficulty(difficulty),
+            spawn.facing_right,
+            clips
+        ))
+        .collect()
 }
 
 impl Enemy {
-    pub fn new(pos: (f32, f32), velocity: (f32, f32)) -> Self {
+    pub fn new(
+        pos: (f32, f32),
+        enemy_type: EnemyType,
+        facing_right: bool,
+        clips: &Clips
+    ) -> Self {
+        let direction = if facing_right { 1.0 } else { -1.0 };
+        let speed = enemy_type.walk_speed;
+        let velocity_y = if enemy_type.flying { speed * FLY_CLIMB } else { 0.0 };
+        let side = if facing_right { "right" } else { "left" };
+        let stand_clip = clips.get(&format!("enemy.{}.stand_{}", enemy_type.sprites, side));
         Enemy {
             pos,
-            velocity,
+            velocity: (direction * speed, velocity_y),
+            enemy_type,
+            angry: false,
+            facing_right,
+            animator: Animator::new(stand_clip),
+            firing: false,
+            behaviour: Behaviour::Patrol,
+            goal: None,
+            think_timer: 0.0,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        let speedup = if self.angry { ANGRY_SPEEDUP } else { 1.0 };
+        self.enemy_type.walk_speed * speedup
+    }
+
+    /// Clip of this enemy's sprite set, like `enemy.robot1.trapped`.
+    pub fn clip(&self, clips: &Clips, action: &str) -> Rc<Clip> {
+        clips.get(&format!("enemy.{}.{}", self.enemy_type.sprites, action))
+    }
+
+    /// Called when the enemy breaks out of a bubble.
+    pub fn enrage(&mut self) {
+        if !self.angry {
+            self.angry = true;
+            self.velocity.0 *= ANGRY_SPEEDUP;
+            self.velocity.1 *= ANGRY_SPEEDUP;
+        }
+    }
+
+    /// Rolls the next behaviour from the weights of the enemy type.
+    fn pick_behaviour(&self, rng: &mut StdRng) -> Behaviour {
+        let enemy_type = &self.enemy_type;
+        let total = enemy_type.chase + enemy_type.patrol + enemy_type.wander;
+        if total <= 0.0 {
+            return Behaviour::Patrol;
+        }
+        let roll = rng.gen::<f32>() * total;
+        if roll < enemy_type.chase {
+            Behaviour::Chase
+        } else if roll < enemy_type.chase + enemy_type.wander {
+            Behaviour::Wander
+        } else {
+            Behaviour::Patrol
+        }
+    }
+
+    fn decide(&mut self, nav: &NavGraph, dt: f32, rng: &mut StdRng) {
+        self.think_timer -= dt;
+        if self.think_timer > 0.0 {
+            return;
+        }
+        self.think_timer = THINK_TIME;
+        self.behaviour = self.pick_behaviour(rng);
+        self.goal = None;
+        if self.behaviour == Behaviour::Wander {
+            let cells = nav.standing_cells();
+            if !cells.is_empty() {
+                self.goal = Some(cells[rng.gen_range(0..cells.len())]);
+            }
+        }
+    }
+
+    /// Steers an enemy standing on a platform along the navigation graph.
+    fn walk(&mut self, nav: &NavGraph, target: (f32, f32), dt: f32) {
+        let cell = pos_to_cell(self.pos);
+        let goal = match self.behaviour {
+            Behaviour::Chase => nav.landing_below(pos_to_cell(target)),
+            Behaviour::Wander => self.goal,
+            Behaviour::Patrol => None,
+        };
+        let speed = self.speed();
+        let step = goal.and_then(|goal| nav.next_step(cell, goal, self.enemy_type.jump_reach()));
+
+        match step {
+            Some(edge) if edge.kind == EdgeKind::Jump => {
+                // Line up below the platform, then jump straight up
+                let center = (cell.0 as f32) + 0.5;
+                if (self.pos.0 - center).abs() <= speed * dt {
+                    self.pos.0 = center;
+                    self.velocity = (0.0, -self.enemy_type.jump_speed);
+                } else {
+                    self.velocity.0 = (center - self.pos.0).signum() * speed;
+                }
+            }
+            // Walk and drop edges both just need walking towards the next cell
+            Some(edge) => {
+                self.velocity.0 = if edge.to.0 < cell.0 { -speed } else { speed };
+            }
+            // Nothing to head for: patrol the platform, turning at its ends. Chasers
+            // without a way to the player wait at the end closest to them
+            None => {
+                let chasing = self.behaviour == Behaviour::Chase;
+                let direction: isize = if chasing {
+                    if target.0 < self.pos.0 { -1 } else { 1 }
+                } else if self.velocity.0 < 0.0 {
+                    -1
+                } else {
+                    1
+                };
+                let center = (cell.0 as f32) + 0.5;
+                let ahead = (cell.0 as isize) + direction;
+                let past_center = (self.pos.0 - center) * (direction as f32) >= 0.0;
+                let at_end = ahead < 0 || !nav.is_standable((ahead as usize, cell.1));
+                self.velocity.0 = match (past_center && at_end, chasing) {
+                    (false, _) => (direction as f32) * speed,
+                    (true, false) => -(direction as f32) * speed,
+                    (true, true) => 0.0,
+                };
+            }
+        }
+    }
+
+    /// Clip for what the enemy is doing in the direction it faces.
+    fn facing_clip(&self, clips: &Clips, action: &str) -> Rc<Clip> {
+        let side = if self.facing_right { "right" } else { "left" };
+        self.clip(clips, &format!("{}_{}", action, side))
+    }
+
+    /// Plays the firing pose, the walk cycle resumes when it has played out.
+    pub fn fire(&mut self, clips: &Clips) {
+        self.firing = true;
+        self.animator.restart(&self.facing_clip(clips, "fire"));
+    }
+
+    pub fn animate(&mut self, clips: &Clips, dt: f32) {
+        if self.velocity.0 != 0.0 {
+            self.facing_right = self.velocity.0 > 0.0;
+        }
+        if self.animator.update(dt).as_deref() == Some("fired") {
+            self.firing = false;
+        }
+        if !self.firing {
+            let action = if self.velocity.0 == 0.0 { "stand" } else { "walk" };
+            self.animator.play(&self.facing_clip(clips, action));
         }
     }
 
-    pub fn update(&mut self, grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT], block_size: f32) {
-        self.velocity.1 += block_size / 50.0; // Gravitation
+    pub fn update(
+        &mut self,
+        grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT],
+        nav: &NavGraph,
+        target: (f32, f32),
+        dt: f32,
+        rng: &mut StdRng
+    ) {
+        self.decide(nav, dt, rng);
+        if self.enemy_type.flying {
+            self.fly(grid, target, dt);
+            return;
+        }
+        if self.velocity.1 == 0.0 {
+            self.walk(nav, target, dt);
+        }
+
+        self.velocity.1 += GRAVITY * dt; // Gravitation
 
         // Horizontale Bewegung prüfen
-        let next_x = self.pos.0 + self.velocity.0;
+        let next_x = self.pos.0 + self.velocity.0 * dt;
         if
             next_x <= 0.0 ||
-            next_x >= (GRID_WIDTH as f32) * block_size ||
-            check_collision(
-                grid,
-                next_x,
-                self.pos.1 + block_size * (block_size / 114.285),
-                block_size
-            )
+            next_x >= (GRID_WIDTH as f32) ||
+            check_collision(grid, next_x, self.pos.1 + COLLISION_MARGIN)
         {
             self.velocity.0 = -self.velocity.0; // Richtung umkehren
         } else {
@@ -54,16 +242,55 @@ impl Enemy {
         }
 
         // Vertikale Bewegung prüfen
-        let next_y = self.pos.1 + self.velocity.1;
+        // Platforms only stop enemies coming from above, so jumps go through
+        // them and a jump that ends inside a platform falls out of it again
+        let next_y = self.pos.1 + self.velocity.1 * dt;
+        let lands =
+            self.velocity.1 >= 0.0 &&
+            (self.pos.1 + COLLISION_MARGIN).floor() < (next_y + COLLISION_MARGIN).floor() &&
+            check_collision(grid, self.pos.0, next_y + COLLISION_MARGIN);
+        if lands || (next_y < 2.0 && self.velocity.1 < 0.0) {
+            self.velocity.1 = 0.0; // Gravitation stoppen
+        } else {
+            self.pos.1 = next_y;
+        }
+        if self.pos.1 > (GRID_HEIGHT as f32) {
+            self.pos.1 = 0.0; // like the player, fall through the floor and come in from the top
+        }
+    }
+
+    /// Flyers ignore gravity and bounce off walls, floors and ceilings. Chasing
+    /// flyers keep turning towards the player's side instead of using the
+    /// navigation graph, the bouncing takes care of the height.
+    fn fly(
+        &mut self,
+        grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT],
+        target: (f32, f32),
+        dt: f32
+    ) {
+        if self.behaviour == Behaviour::Chase {
+            self.velocity.0 = self.velocity.0.abs() * (target.0 - self.pos.0).signum();
+        }
+        let next_x = self.pos.0 + self.velocity.0 * dt;
         if
-            check_collision(
-                grid,
-                self.pos.0,
-                next_y + block_size * (block_size / 114.285),
-                block_size
-            )
+            next_x <= 2.0 ||
+            next_x >= ((GRID_WIDTH - 2) as f32) ||
+            check_collision(grid, next_x, self.pos.1 - 1.0)
         {
-            self.velocity.1 = 0.0; // Gravitation stoppen
+            self.velocity.0 = -self.velocity.0;
+        } else {
+            self.pos.0 = next_x;
+        }
+
+        // Feet going down, head going up
+        let next_y = self.pos.1 + self.velocity.1 * dt;
+        let probe_y = if self.velocity.1 > 0.0 { next_y } else { next_y - 2.0 };
+        if
+            probe_y <= 1.0 ||
+            next_y >= ((GRID_HEIGHT - 1) as f32) ||
+            check_collision(grid, self.pos.0, probe_y)
+        {
+            self.velocity.1 = -self.velocity.1;
         } else {
             self.pos.1 = next_y;
         }
@@ -74,33 +301,25 @@ impl Enemy {
         game: &mut Game,
         _ctx: &mut ggez::Context
     ) -> Result<(), Box<dyn std::error::Error>> {
-        for enemy in &game.enemies {
-            let image = if enemy.velocity.0 < 0.0 {
-                &game.resources.enemy_images[0]
+        let world = &game.world;
+        for enemy in &world.enemies {
+            let image = game.resources.frame(enemy.animator.frame());
+            let tint = if enemy.angry {
+                Color::new(1.0, 0.4, 0.4, 1.0)
             } else {
-                &game.resources.enemy_images[1]
+                let (r, g, b) = enemy.enemy_type.tint;
+                Color::new(r, g, b, 1.0)
             };
-            canvas.draw(
-                image,
-                DrawParam::default()
-                    .dest(ggez::mint::Point2 {
-                        x: enemy.pos.0 - game.block_size,
-                        y: enemy.pos.1 -
-                        get_y_pos_correction(game.window_width, game.block_size, image),
-                    })
-                    .scale(ggez::mint::Vector2 {
-                        x: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285,
-                        y: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285,
-                    })
-            );
+            let pos = (enemy.pos.0 - 1.0, enemy.pos.1 - 2.5);
+            canvas.draw(image, game.camera.sprite(pos, 1.0).color(tint));
         }
         Ok(())
     }
 
-    pub fn is_off_screen(&self, block_size: f32) -> bool {
+    pub fn is_off_screen(&self) -> bool {
         self.pos.0 < 0.0 ||
-            self.pos.0 > (GRID_WIDTH as f32) * block_size + 2.0 * block_size ||
+            self.pos.0 > (GRID_WIDTH as f32) + 2.0 ||
             self.pos.1 < 0.0 ||
-            self.pos.1 > (GRID_HEIGHT as f32) * block_size + 2.0 * block_size
+            self.pos.1 > (GRID_HEIGHT as f32) + 2.0
     }
 }
@@ -1,39 +1,31 @@
-use ggez::graphics::DrawParam;
-
-use crate::{ game::Game, grid::GRID_WIDTH };
+use crate::{ animation::Animator, game::Game, grid::{ GRID_HEIGHT, GRID_WIDTH } };
 
 pub struct EnemyBullet {
     pub pos: (f32, f32),
     pub velocity: (f32, f32),
+    pub animator: Animator,
 }
 
 impl EnemyBullet {
-    pub fn update(&mut self) {
-        self.pos.0 += self.velocity.0;
-        self.pos.1 += self.velocity.1;
+    pub fn update(&mut self, dt: f32) {
+        self.pos.0 += self.velocity.0 * dt;
+        self.pos.1 += self.velocity.1 * dt;
+        self.animator.update(dt);
     }
 
     pub fn is_off_screen(&self) -> bool {
-        self.pos.0 < 0.0 || self.pos.0 > 800.0 || self.pos.1 < 0.0 || self.pos.1 > 480.0
+        self.pos.0 < 0.0 ||
+            self.pos.0 > (GRID_WIDTH as f32) ||
+            self.pos.1 < 0.0 ||
+            self.pos.1 > (GRID_HEIGHT as f32)
     }
 
     pub fn draw(canvas: &mut ggez::graphics::Canvas, game: &mut Game) {
-        for bullet in &game.enemy_bullets {
-            let image = game.resources.enemy_bullet_image[0].clone();
-            canvas.draw(
-                &image,
-                DrawParam::default()
-                    .dest(ggez::mint::Point2 {
-                        x: bullet.pos.0,
-                        y: bullet.pos.1 - game.block_size,
-                    })
-                    .scale(ggez::mint::Vector2 {
-                        x: (game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285) /
-                        2.0,
-                        y: (game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285) /
-                        2.0,
-                    })
-            );
+        let world = &game.world;
+        for bullet in &world.enemy_bullets {
+            let image = game.resources.frame(bullet.animator.frame());
+            let pos = (bullet.pos.0, bullet.pos.1 - 1.0);
+            canvas.draw(image, game.camera.sprite(pos, 0.5));
         }
     }
 }
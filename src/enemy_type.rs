@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::utils::GRAVITY;
+use crate::world::Difficulty;
+
+/// Enemy types keyed by the letter used for them in level maps. One block per
+/// type, started by the letter in brackets and followed by `key value` lines:
+///
+/// * `name` shown nowhere yet, but makes the file readable
+/// * `sprites` sprite set, the `enemy.<sprites>.*` assets and clips
+/// * `tint` color multiplied onto the sprite, `r g b` from 0 to 1
+/// * `walk` walking (or flying) speed in tiles per second
+/// * `flying` `yes` to ignore gravity and bounce off floors and ceilings
+/// * `jump` jump speed in tiles per second, 0 for enemies that never jump
+/// * `chase` `patrol` `wander` how often the enemy picks each behaviour, as
+///   relative weights: chasers head for the player, patrollers walk their
+///   platform end to end and wanderers make for a random spot in the level;
+///   types without any weight patrol
+/// * `fire_rate` chance per second to fire a projectile
+/// * `projectile` `none`, `bolt` (straight ahead) or `spark` (aimed at the player)
+///
+/// Keys left out keep the values of `EnemyType::default()`, lines starting with
+/// `;` are comments.
+pub const ENEMY_TYPES_PATH: &str = "resources/enemies.txt";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projectile {
+    Bolt,
+    Spark,
+}
+
+impl Projectile {
+    /// Speed in tiles per second.
+    pub fn speed(self) -> f32 {
+        match self {
+            Projectile::Bolt => 20.0,
+            Projectile::Spark => 12.0,
+        }
+    }
+
+    /// Animation clip the projectile is drawn with.
+    pub fn clip_name(self) -> &'static str {
+        match self {
+            Projectile::Bolt => "projectile.bolt",
+            Projectile::Spark => "projectile.spark",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemyType {
+    pub kind: char,
+    pub name: String,
+    pub sprites: String,
+    pub tint: (f32, f32, f32),
+    pub walk_speed: f32,
+    pub flying: bool,
+    pub jump_speed: f32,
+    pub chase: f32,
+    pub patrol: f32,
+    pub wander: f32,
+    pub fire_rate: f32,
+    pub projectile: Option<Projectile>,
+}
+
+impl Default for EnemyType {
+    /// A plain walker, also used for map letters that have no type defined.
+    fn default() -> Self {
+        EnemyType {
+            kind: 'a',
+            name: "walker".to_string(),
+            sprites: "robot0".to_string(),
+            tint: (1.0, 1.0, 1.0),
+            walk_speed: 4.0,
+            flying: false,
+            jump_speed: 0.0,
+            chase: 0.0,
+            patrol: 0.0,
+            wander: 0.0,
+            fire_rate: 0.3,
+            projectile: Some(Projectile::Bolt),
+        }
+    }
+}
+
+impl EnemyType {
+    /// The type as enemies meet it at `difficulty`.
+    pub fn for_difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.walk_speed *= difficulty.enemy_speed();
+        self
+    }
+
+    /// Number of cells a jump climbs.
+    pub fn jump_reach(&self) -> usize {
+        ((self.jump_speed * self.jump_speed) / (2.0 * GRAVITY)) as usize
+    }
+}
+
+#[derive(Debug)]
+pub enum EnemyTypeError {
+    Io(PathBuf, std::io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for EnemyTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnemyTypeError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            EnemyTypeError::Parse { path, line, message } =>
+                write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct EnemyTypes {
+    types: HashMap<char, EnemyType>,
+}
+
+impl EnemyTypes {
+    pub fn load() -> Result<Self, EnemyTypeError> {
+        let path = PathBuf::from(ENEMY_TYPES_PATH);
+        let text = fs::read_to_string(&path).map_err(|err| EnemyTypeError::Io(path.clone(), err))?;
+        EnemyTypes::parse(&text).map_err(|(line, message)| EnemyTypeError::Parse {
+            path,
+            line,
+            message,
+        })
+    }
+
+    /// The type for a map letter, falling back to the default walker.
+    pub fn get(&self, kind: char) -> EnemyType {
+        self.types
+            .get(&kind)
+            .cloned()
+            .unwrap_or_else(|| EnemyType { kind, ..EnemyType::default() })
+    }
+
+    /// Parses the type file, reporting problems as 1-based `(line, message)`.
+    pub fn parse(text: &str) -> Result<Self, (usize, String)> {
+        let mut types = HashMap::new();
+        let mut current: Option<EnemyType> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                let kind = match header.chars().collect::<Vec<_>>()[..] {
+                    [kind] if kind.is_ascii_lowercase() => kind,
+                    _ => {
+                        return Err((number, format!("'{}' is not a letter from a-z", header)));
+                    }
+                };
+                if types.contains_key(&kind) || current.as_ref().is_some_and(|t| t.kind == kind) {
+                    return Err((number, format!("type '{}' is defined twice", kind)));
+                }
+                if let Some(done) = current.replace(EnemyType { kind, ..EnemyType::default() }) {
+                    types.insert(done.kind, done);
+                }
+                continue;
+            }
+
+            let enemy_type = current
+                .as_mut()
+                .ok_or((number, "expected a type header like '[a]'".to_string()))?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let bad_value = || (number, format!("bad value for '{}'", fields[0]));
+            let number_value = |value: &str| value.parse::<f32>().map_err(|_| bad_value());
+            match fields[..] {
+                ["name", name] => {
+                    enemy_type.name = name.to_string();
+                }
+                ["sprites", sprites] => {
+                    enemy_type.sprites = sprites.to_string();
+                }
+                ["tint", r, g, b] => {
+                    enemy_type.tint = (number_value(r)?, number_value(g)?, number_value(b)?);
+                }
+                ["walk", speed] => {
+                    enemy_type.walk_speed = number_value(speed)?;
+                }
+                ["flying", flying] => {
+                    enemy_type.flying = match flying {
+                        "yes" => true,
+                        "no" => false,
+                        _ => {
+                            return Err(bad_value());
+                        }
+                    };
+                }
+                ["jump", speed] => {
+                    enemy_type.jump_speed = number_value(speed)?;
+                }
+                ["chase", weight] => {
+                    enemy_type.chase = number_value(weight)?;
+                }
+                ["patrol", weight] => {
+                    enemy_type.patrol = number_value(weight)?;
+                }
+                ["wander", weight] => {
+                    enemy_type.wander = number_value(weight)?;
+                }
+                ["fire_rate", rate] => {
+                    enemy_type.fire_rate = number_value(rate)?;
+                }
+                ["projectile", projectile] => {
+                    enemy_type.projectile = match projectile {
+                        "none" => None,
+                        "bolt" => Some(Projectile::Bolt),
+                        "spark" => Some(Projectile::Spark),
+                        _ => {
+                            return Err(bad_value());
+                        }
+                    };
+                }
+                _ => {
+                    return Err((number, format!("unknown entry '{}'", line)));
+                }
+            }
+        }
+        if let Some(done) = current {
+            types.insert(done.kind, done);
+        }
+        Ok(EnemyTypes { types })
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use ggez::graphics::{ Canvas, Color, DrawParam, Image, ImageFormat };
+use ggez::mint::{ Point2, Vector2 };
+
+use crate::resources::Resources;
+
+/// Height of the glyph art in pixels, at scale 1.
+pub const GLYPH_HEIGHT: f32 = 28.0;
+/// Gap between two glyphs and between two lines, in pixels at scale 1.
+const GLYPH_SPACING: f32 = 1.0;
+const LINE_SPACING: f32 = 6.0;
+/// Width of a space; the space glyph of the art is a single pixel.
+const SPACE_WIDTH: f32 = 14.0;
+
+/// Punctuation the art doesn't have, as 7 rows of pixel blocks. Drawn in the
+/// colors of the art at startup so menus and the HUD can use it too.
+const EXTRA_GLYPHS: &[(char, [&str; 7])] = &[
+    ('.', ["..", "..", "..", "..", "..", "##", "##"]),
+    (',', ["..", "..", "..", "..", "##", ".#", "#."]),
+    (':', ["..", "##", "##", "..", "##", "##", ".."]),
+    ('!', ["##", "##", "##", "##", "..", "##", "##"]),
+    ('\'', ["##", "##", ".#", "..", "..", "..", ".."]),
+    ('?', [".###.", "#...#", "....#", "..##.", "..#..", ".....", "..#.."]),
+    ('-', ["....", "....", "....", "####", "....", "....", "...."]),
+    ('_', ["....", "....", "....", "....", "....", "....", "####"]),
+    ('=', ["....", "....", "####", "....", "####", "....", "...."]),
+    ('+', [".....", "..#..", "..#..", "#####", "..#..", "..#..", "....."]),
+    ('/', ["....#", "...#.", "...#.", "..#..", ".#...", ".#...", "#...."]),
+    ('<', ["...#", "..#.", ".#..", "#...", ".#..", "..#.", "...#"]),
+    ('>', ["#...", ".#..", "..#.", "...#", "..#.", ".#..", "#..."]),
+    ('(', [".#", "#.", "#.", "#.", "#.", "#.", ".#"]),
+    (')', ["#.", ".#", ".#", ".#", ".#", ".#", "#."]),
+];
+/// Pixels per block of an extra glyph, and the outline around the blocks.
+const BLOCK_SIZE: usize = 3;
+const OUTLINE: usize = 2;
+const GLYPH_FILL: [u8; 4] = [40, 150, 240, 255];
+const GLYPH_OUTLINE: [u8; 4] = [0, 10, 40, 255];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Draws text with the game's own glyphs: `font.digits` and `font.letters`
+/// from the asset manifest, plus a few generated punctuation marks. Lower case
+/// is drawn in upper case; characters without a glyph leave a gap.
+pub struct BitmapFont {
+    glyphs: HashMap<char, Image>,
+}
+
+impl BitmapFont {
+    pub fn new(ctx: &ggez::Context, resources: &Resources) -> Self {
+        let mut glyphs = HashMap::new();
+        for (chars, name) in [('0'..='9', "font.digits"), ('A'..='Z', "font.letters")] {
+            for (c, image) in chars.zip(resources.images(name)) {
+                glyphs.insert(c, image.clone());
+            }
+        }
+        for (c, rows) in EXTRA_GLYPHS {
+            glyphs.entry(*c).or_insert_with(|| extra_glyph(ctx, rows));
+        }
+        BitmapFont { glyphs }
+    }
+
+    fn glyph(&self, c: char) -> Option<&Image> {
+        self.glyphs.get(&c.to_ascii_uppercase())
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        match self.glyph(c) {
+            Some(image) => (image.width() as f32) + GLYPH_SPACING,
+            None => SPACE_WIDTH,
+        }
+    }
+
+    fn line_width(&self, line: &str) -> f32 {
+        let width: f32 = line.chars().map(|c| self.advance(c)).sum();
+        (width - GLYPH_SPACING).max(0.0)
+    }
+
+    /// Size of `text` in pixels, lines separated by `\n`.
+    pub fn measure(&self, text: &str, scale: f32) -> (f32, f32) {
+        let width = text.lines().map(|line| self.line_width(line)).fold(0.0, f32::max);
+        let lines = text.lines().count().max(1) as f32;
+        (width * scale, (lines * GLYPH_HEIGHT + (lines - 1.0) * LINE_SPACING) * scale)
+    }
+
+    /// Draws `text` with its top at `pos.1`, and its left edge, center or right
+    /// edge at `pos.0`, line by line. `color` is multiplied onto the glyphs.
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas,
+        text: &str,
+        pos: (f32, f32),
+        align: Align,
+        color: Color,
+        scale: f32
+    ) {
+        for (index, line) in text.lines().enumerate() {
+            let width = self.line_width(line) * scale;
+            let mut x = match align {
+                Align::Left => pos.0,
+                Align::Center => pos.0 - width / 2.0,
+                Align::Right => pos.0 - width,
+            };
+            let y = pos.1 + (index as f32) * (GLYPH_HEIGHT + LINE_SPACING) * scale;
+            for c in line.chars() {
+                if let Some(image) = self.glyph(c) {
+                    canvas.draw(
+                        image,
+                        DrawParam::default()
+                            .dest(Point2 { x: x.round(), y: y.round() })
+                            .scale(Vector2 { x: scale, y: scale })
+                            .color(color)
+                    );
+                }
+                x += self.advance(c) * scale;
+            }
+        }
+    }
+}
+
+/// Blows up a punctuation pattern into glyph art: filled blocks with a dark
+/// outline, as tall as the glyphs of the art.
+fn extra_glyph(ctx: &ggez::Context, rows: &[&str; 7]) -> Image {
+    let columns = rows[0].len();
+    let width = columns * BLOCK_SIZE + 2 * OUTLINE;
+    let height = GLYPH_HEIGHT as usize;
+    let top = (height - rows.len() * BLOCK_SIZE) / 2;
+    let filled = |x: isize, y: isize| {
+        let (x, y) = (x - (OUTLINE as isize), y - (top as isize));
+        x >= 0 &&
+            y >= 0 &&
+            rows
+                .get((y as usize) / BLOCK_SIZE)
+                .and_then(|row| row.as_bytes().get((x as usize) / BLOCK_SIZE))
+                .is_some_and(|&block| block == b'#')
+    };
+
+    let mut pixels = vec![];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let outline = (-(OUTLINE as isize)..=OUTLINE as isize).any(|dy| {
+                (-(OUTLINE as isize)..=OUTLINE as isize).any(|dx| filled(x + dx, y + dy))
+            });
+            pixels.extend_from_slice(if filled(x, y) {
+                &GLYPH_FILL
+            } else if outline {
+                &GLYPH_OUTLINE
+            } else {
+                &[0, 0, 0, 0]
+            });
+        }
+    }
+    Image::from_pixels(ctx, &pixels, ImageFormat::Rgba8UnormSrgb, width as u32, height as u32)
+}
@@ -1,16 +1,30 @@
 use std::process::exit;
 
-use ggez::audio::{SoundSource, Source};
-use ggez::event::EventHandler;
+use ggez::conf::FullscreenType;
+use ggez::event::{ Axis, Button as PadButton, EventHandler, GamepadId };
 use ggez::graphics::DrawParam;
-use crate::grid::{ GridConfig, GRID_HEIGHT, GRID_WIDTH };
+use crate::actions::{ conflict, Action, ActionState, Direction };
+use crate::audio::{ self, Audio };
+use crate::camera::{ Camera, MIN_SCREEN };
+use crate::assets::AssetManifest;
+use crate::data::GameData;
+use crate::font::{ Align, BitmapFont };
+use crate::gamepad::{ Pad, PadInput };
+use crate::highscores::{ today, HighScore, HighScores, NameEntry };
+use crate::keys::key_name;
+use crate::level::{ level_path, Level, LEVEL_DIR };
 use crate::resources::Resources;
+use crate::replay::{ Replay, ReplayEvent };
+use crate::settings::Settings;
+use crate::watcher::Watcher;
+use crate::world::{ Button, Input, World, FIXED_DT, TICKS_PER_SECOND };
 use ggez::input::keyboard::{ KeyCode, KeyInput };
-use crate::{ player, utils };
+use crate::player::{ self, PLAYER_NAMES };
 use crate::grid;
 use crate::enemy;
 use crate::bullet;
 use crate::bubble;
+use crate::item;
 use crate::enemy_bullet;
 use ggez::graphics;
 
@@ -18,176 +32,921 @@ use ggez::graphics;
 pub enum GameState {
     Menu,
     Play,
+    /// Play stopped mid-level, the pause menu over the frozen playfield.
+    Paused,
+    /// Sound options, reached from the pause menu.
+    Options,
+    LevelTransition,
+    Victory,
     GameOver,
+    /// The key bindings, reached from the menu.
+    Controls,
+    /// Asks a player with a new high score for their name after a run.
+    NameEntry,
+    /// The high score table, from the menu or after entering a name.
+    HighScores,
+    /// A critical asset is broken; shows what failed until the window is closed.
+    Error(String),
+}
+
+/// How long the interstitial between two levels is shown, in seconds.
+const LEVEL_TRANSITION_TIME: f32 = 2.5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuItem {
+    Start,
+    Players,
+    WindowSize,
+    Controls,
+    HighScores,
+    Exit,
+}
+
+const MENU_ITEMS: [MenuItem; 6] = [
+    MenuItem::Start,
+    MenuItem::Players,
+    MenuItem::WindowSize,
+    MenuItem::Controls,
+    MenuItem::HighScores,
+    MenuItem::Exit,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum PauseItem {
+    Resume,
+    RestartLevel,
+    Options,
+    QuitToMenu,
+}
+
+const PAUSE_ITEMS: [PauseItem; 4] = [
+    PauseItem::Resume,
+    PauseItem::RestartLevel,
+    PauseItem::Options,
+    PauseItem::QuitToMenu,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum OptionItem {
+    MusicVolume,
+    SoundVolume,
+    Mute,
+    Back,
+}
+
+const OPTION_ITEMS: [OptionItem; 4] = [
+    OptionItem::MusicVolume,
+    OptionItem::SoundVolume,
+    OptionItem::Mute,
+    OptionItem::Back,
+];
+
+/// Change of a volume per step on the options screen.
+const VOLUME_STEP: f32 = 0.1;
+
+/// The bindings listed on the controls screen: every action of the first
+/// player, the gameplay ones of the second. Followed by a reset and a back row.
+fn control_rows() -> Vec<(usize, Action)> {
+    let first = Action::ALL.into_iter().map(|action| (0, action));
+    let second = Action::ALL
+        .into_iter()
+        .filter(|action| action.in_game())
+        .map(|action| (1, action));
+    first.chain(second).collect()
+}
+
+fn control_label(player: usize, action: Action) -> String {
+    if action.in_game() {
+        format!("{} {}", PLAYER_NAMES[player], action.label())
+    } else {
+        action.label().to_string()
+    }
 }
 
 pub struct Game {
     pub state: GameState,
-    pub score: i32,
-    pub player: player::Player,
-    pub enemies: Vec<enemy::Enemy>,
-    pub bullets: Vec<bullet::Bullet>,
-    pub bubbles: Vec<bubble::Bubble>,
-    pub enemy_bullets: Vec<enemy_bullet::EnemyBullet>,
-    pub grid: [[bool; GRID_WIDTH]; GRID_HEIGHT],
-    pub enemy_spawn_timer: f32,
+    pub world: World,
+    pub level: Level,
+    pub players: usize, // picked in the menu for the next run
+    pub actions: [ActionState; 2], // by player
+    pub inputs: Vec<Input>, // of the last tick, to record only what changed
+    pub recording: Vec<ReplayEvent>,
+    pub restarts: Vec<u64>, // ticks at which the level was restarted, for the replay
+    pub transition_timer: f32,
     pub resources: Resources,
-    pub window_width: f32,
-    pub window_height: f32,
+    pub font: BitmapFont,
+    pub camera: Camera,
+    pub settings: Settings,
+    pub pads: Vec<(GamepadId, Pad)>,
     pub selected_menu_option: usize,
+    pub selected_control: usize,
+    pub selected_pause_option: usize,
+    pub selected_option: usize,
+    pub rebinding: Option<(usize, Action)>, // waiting for a key for this player's action
+    pub controls_message: String,
+    pub high_scores: HighScores,
+    pub name_entry: Option<NameEntry>,
+    pub new_high_score: Option<usize>, // rank to highlight in the table
     pub selected_window_size: usize,
     pub window_sizes: Vec<(f32, f32)>,
-    pub block_size: f32,
-    pub music: Source,
+    pub audio: Audio,
+    pub watcher: Option<Watcher>, // only in --dev
 }
 
 impl Game {
-    pub fn new(ctx: &mut ggez::Context) -> Self {
-        let level1_config = GridConfig {
-            vertical_lines: vec![
-                (0, 0, GRID_HEIGHT),
-                (1, 0, GRID_HEIGHT),
-                (GRID_WIDTH - 2, 0, GRID_HEIGHT),
-                (GRID_WIDTH - 1, 0, GRID_HEIGHT)
-            ],
-            horizontal_lines: vec![
-                (0, 1, 8),
-                (0, 12, 20),
-                (0, 24, 32),
-                (5, 5, 10),
-                (5, 22, 27),
-                (9, 5, 27),
-                (13, 1, 11),
-                (13, 21, 32),
-                (17, 1, 8),
-                (17, 12, 20),
-                (17, 24, 32)
-            ],
-        };
+    pub fn new(ctx: &mut ggez::Context, settings: Settings, dev: bool) -> Self {
+        // Broken files get the error screen, with empty stand-ins behind it
+        let mut state = GameState::Menu;
+        let level = Level::load(1).unwrap_or_else(|err| {
+            eprintln!("Could not load level: {}", err);
+            state = GameState::Error(err.to_string());
+            Level::empty(1)
+        });
+        let data = GameData::load().unwrap_or_else(|err| {
+            eprintln!("Could not load game data: {}", err);
+            state = GameState::Error(err.to_string());
+            GameData::default()
+        });
+        let resources = load_resources(ctx, &data.manifest, &mut state);
+        let font = BitmapFont::new(ctx, &resources);
+        let audio = Audio::new(&resources, &settings);
+
+        let high_scores = HighScores::load().unwrap_or_else(|err| {
+            eprintln!("Could not read high scores, starting a new table: {}", err);
+            HighScores::default()
+        });
 
-        let grid = grid::create_grid(&level1_config);
-        let (width, height) = ctx.gfx.drawable_size();
-        let block_size = width / (GRID_WIDTH as f32);
-        let enemies = enemy::create_enemies(width, height, block_size);
-        let resources = Resources::load(ctx);
-        let mut music = Source::new(ctx, "/sounds/theme.ogg").unwrap();
-        music.set_repeat(true);
+        let window_sizes = vec![(800.0, 480.0), (1024.0, 768.0), (1280.0, 720.0), (1920.0, 1080.0)];
 
         Game {
-            state: GameState::Menu,
-            score: 0,
-            player: player::Player::new(400.0, 240.0),
-            enemies,
-            bullets: vec![],
-            bubbles: vec![],
-            enemy_bullets: vec![],
-            grid,
-            enemy_spawn_timer: 10.0,
+            state,
+            world: World::new(rand::random(), settings.difficulty, 1, &level, data),
+            level,
+            players: 1,
+            actions: Default::default(),
+            inputs: vec![Input::default()],
+            recording: vec![],
+            restarts: vec![],
+            transition_timer: 0.0,
             resources,
-            window_width: width,
-            window_height: height,
+            font,
+            camera: Camera::fit(ctx.gfx.drawable_size()),
             selected_menu_option: 0,
-            selected_window_size: 0,
-            window_sizes: vec![(800.0, 480.0), (1024.0, 768.0), (1280.0, 720.0), (1920.0, 1080.0)],
-            block_size,
-            music
+            selected_control: 0,
+            selected_pause_option: 0,
+            selected_option: 0,
+            rebinding: None,
+            controls_message: String::new(),
+            high_scores,
+            name_entry: None,
+            new_high_score: None,
+            selected_window_size: window_sizes
+                .iter()
+                .position(|&size| size == settings.window)
+                .unwrap_or(0),
+            window_sizes,
+            settings,
+            pads: vec![],
+            audio,
+            watcher: dev.then(|| Watcher::new("resources")),
         }
     }
 
     pub fn reset(&mut self, _ctx: &mut ggez::Context) {
-        self.score = 0;
-        self.player = player::Player::new(self.window_width / 2.0, self.window_width / 2.0);
-        self.enemies = enemy::create_enemies(
-            self.window_width,
-            self.window_height,
-            self.block_size
+        // Re-read the map so edits to the level file show up on the next run
+        match Level::load(1) {
+            Ok(level) => {
+                self.level = level;
+            }
+            Err(err) => eprintln!("Could not reload level, keeping the old one: {}", err),
+        }
+        let data = GameData::load().unwrap_or_else(|err| {
+            eprintln!("Could not reload game data, keeping the old one: {}", err);
+            self.world.data.clone()
+        });
+        self.world = World::new(
+            rand::random(),
+            self.settings.difficulty,
+            self.players,
+            &self.level,
+            data
         );
-        self.bullets = vec![];
-        self.bubbles = vec![];
-        self.enemy_bullets = vec![];
+        self.actions.iter_mut().for_each(ActionState::clear);
+        self.inputs = vec![Input::default(); self.players];
+        self.recording.clear();
+        self.restarts.clear();
+    }
+
+    fn start_game(&mut self, ctx: &mut ggez::Context) {
+        self.reset(ctx);
+        self.audio.play_music();
+        self.state = GameState::Play;
+    }
+
+    /// Records how a player's `input` differs from the last tick's for the replay.
+    fn record(&mut self, player: usize, input: Input) {
+        let tick = self.world.tick;
+        let last = self.inputs[player];
+        let changes = [
+            (Button::Left, input.left, input.left != last.left),
+            (Button::Right, input.right, input.right != last.right),
+            (Button::Jump, true, input.jump),
+            (Button::Shoot, true, input.shoot),
+        ];
+        for (button, pressed, changed) in changes {
+            if changed {
+                self.recording.push(ReplayEvent { tick, player, button, pressed });
+            }
+        }
+        self.inputs[player] = input;
+    }
+
+    /// After a run: asks the next player from `first` on with a score good
+    /// enough for the table for their name, else shows the table if it got a
+    /// new entry, else goes back to the menu.
+    fn enter_high_scores(&mut self, ctx: &mut ggez::Context, first: usize) {
+        let next = self.world.players
+            .iter()
+            .enumerate()
+            .skip(first)
+            .find(|(_, player)| self.high_scores.qualifies(player.score));
+        if let Some((player, _)) = next {
+            self.name_entry = Some(NameEntry::new(player));
+            self.state = GameState::NameEntry;
+        } else if self.new_high_score.is_some() {
+            self.state = GameState::HighScores;
+        } else {
+            self.state = GameState::Menu;
+            self.reset(ctx);
+        }
+    }
+
+    /// Puts the entered name in the table and moves on to the next player.
+    fn finish_name_entry(&mut self, ctx: &mut ggez::Context) {
+        let Some(entry) = self.name_entry.take() else {
+            return;
+        };
+        let rank = self.high_scores.insert(HighScore {
+            name: entry.name(),
+            score: self.world.players[entry.player].score,
+            level: self.world.level,
+            date: today(),
+        });
+        self.new_high_score = Some(rank);
+        if let Err(err) = self.high_scores.save() {
+            eprintln!("Could not save high scores: {}", err);
+        }
+        self.enter_high_scores(ctx, entry.player + 1);
+    }
+
+    fn name_entry_input(
+        &mut self,
+        ctx: &mut ggez::Context,
+        direction: Option<Direction>,
+        confirm: bool,
+        back: bool
+    ) {
+        let Some(entry) = &mut self.name_entry else {
+            return;
+        };
+        match direction {
+            Some(Direction::Up) => entry.step_letter(1),
+            Some(Direction::Down) => entry.step_letter(-1),
+            Some(Direction::Left) => entry.previous(),
+            Some(Direction::Right) => entry.next(),
+            None if confirm && entry.is_on_last_letter() => self.finish_name_entry(ctx),
+            None if confirm => entry.next(),
+            None if back => entry.previous(),
+            None => {}
+        }
+    }
+
+    /// Everyone's score, one line per player.
+    fn score_lines(&self) -> String {
+        if let [player] = &self.world.players[..] {
+            return format!("Score: {}", player.score);
+        }
+        let lines: Vec<String> = self.world.players
+            .iter()
+            .zip(PLAYER_NAMES)
+            .map(|(player, name)| format!("{}: {}", name, player.score))
+            .collect();
+        lines.join("\n")
+    }
+
+    /// The playfield with everything in it, and the HUD.
+    fn draw_play(
+        &mut self,
+        canvas: &mut graphics::Canvas,
+        ctx: &mut ggez::Context
+    ) -> ggez::GameResult {
+        if self.camera.has_playfield() {
+            canvas.set_scissor_rect(self.camera.playfield())?;
+            let _ = grid::draw(canvas, self);
+            player::Player::draw(canvas, self);
+            let _ = enemy::Enemy::draw(canvas, self, ctx);
+            bullet::Bullet::draw(canvas, self);
+            bubble::Bubble::draw(canvas, self);
+            item::Item::draw(canvas, self);
+            enemy_bullet::EnemyBullet::draw(canvas, self);
+            canvas.set_default_scissor_rect();
+        }
+        player::Player::draw_hud(canvas, self);
+
+        // Scores in the top corners, the first player's on the left
+        if let [player] = &self.world.players[..] {
+            self.font.draw(
+                canvas,
+                &format!("Score: {}", player.score),
+                (10.0, 10.0),
+                Align::Left,
+                graphics::Color::WHITE,
+                0.6
+            );
+        } else {
+            for (index, player) in self.world.players.iter().enumerate() {
+                let (x, align) = if index == 0 {
+                    (10.0, Align::Left)
+                } else {
+                    (self.camera.screen.0 - 10.0, Align::Right)
+                };
+                self.font.draw(
+                    canvas,
+                    &format!("{}: {}", PLAYER_NAMES[index], player.score),
+                    (x, 10.0),
+                    align,
+                    player::tint(index),
+                    0.6
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Dims the frozen playfield and lists the pause menu, or the options
+    /// picked from it, on top.
+    fn draw_pause_menu(&self, canvas: &mut graphics::Canvas) {
+        let (width, height) = self.camera.screen;
+        canvas.draw(
+            &graphics::Quad,
+            DrawParam::default()
+                .dest_rect(graphics::Rect::new(0.0, 0.0, width, height))
+                .color(graphics::Color::new(0.0, 0.0, 0.0, 0.6))
+        );
+
+        let (title, labels, selected) = if self.state == GameState::Options {
+            let percent = |volume: f32| (volume * 100.0).round() as u32;
+            let labels: Vec<String> = OPTION_ITEMS.into_iter()
+                .map(|item| match item {
+                    OptionItem::MusicVolume =>
+                        format!("< MUSIC {}% >", percent(self.settings.music_volume)),
+                    OptionItem::SoundVolume =>
+                        format!("< SOUND {}% >", percent(self.settings.sfx_volume)),
+                    OptionItem::Mute if self.audio.is_muted() => "< MUTED: YES >".to_string(),
+                    OptionItem::Mute => "< MUTED: NO >".to_string(),
+                    OptionItem::Back => "BACK".to_string(),
+                })
+                .collect();
+            ("OPTIONS", labels, self.selected_option)
+        } else {
+            let labels: Vec<String> = PAUSE_ITEMS.into_iter()
+                .map(|item| {
+                    match item {
+                        PauseItem::Resume => "RESUME",
+                        PauseItem::RestartLevel => "RESTART LEVEL",
+                        PauseItem::Options => "OPTIONS",
+                        PauseItem::QuitToMenu => "QUIT TO MENU",
+                    }.to_string()
+                })
+                .collect();
+            ("PAUSED", labels, self.selected_pause_option)
+        };
+
+        let center = width / 2.0;
+        let top = height / 2.0 - 150.0;
+        let white = graphics::Color::WHITE;
+        self.font.draw(canvas, title, (center, top), Align::Center, white, 1.25);
+        for (i, label) in labels.iter().enumerate() {
+            let color = if i == selected {
+                graphics::Color::WHITE
+            } else {
+                graphics::Color::new(0.5, 0.5, 0.5, 1.0)
+            };
+            let y = top + 80.0 + (i as f32) * 50.0;
+            self.font.draw(canvas, label, (center, y), Align::Center, color, 1.0);
+        }
+    }
+
+    /// Draws `text` in the middle of the screen, for the screens between levels.
+    fn draw_centered(&self, canvas: &mut graphics::Canvas, text: &str) {
+        let (width, height) = self.camera.screen;
+        let (_, text_height) = self.font.measure(text, 0.75);
+        let pos = (width / 2.0, (height - text_height) / 2.0);
+        self.font.draw(canvas, text, pos, Align::Center, graphics::Color::WHITE, 0.75);
+    }
+
+    fn save_replay(&mut self) {
+        let replay = Replay::from_run(
+            &self.world,
+            std::mem::take(&mut self.restarts),
+            std::mem::take(&mut self.recording)
+        );
+        match replay.save() {
+            Ok(path) => println!("Replay saved to {}", path.display()),
+            Err(err) => eprintln!("Could not save replay: {}", err),
+        }
+    }
+
+    /// Called once the current level is cleared: queues the next level behind the
+    /// interstitial screen, or ends the run when this was the last one.
+    fn finish_level(&mut self) {
+        match Level::load_next(self.level.id) {
+            Ok(Some(level)) => {
+                self.level = level;
+                self.transition_timer = LEVEL_TRANSITION_TIME;
+                self.state = GameState::LevelTransition;
+            }
+            Ok(None) => {
+                self.save_replay();
+                self.state = GameState::Victory;
+            }
+            Err(err) => {
+                eprintln!("Could not load the next level: {}", err);
+                self.save_replay();
+                self.state = GameState::Menu;
+            }
+        }
+    }
+
+    /// In `--dev`, picks up edited resources: levels get their layout swapped
+    /// under the running game, anything else reloads all data and images.
+    fn hot_reload(&mut self, ctx: &mut ggez::Context) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let changed = watcher.poll(ctx.time.delta().as_secs_f32());
+        if changed.is_empty() {
+            return;
+        }
+
+        if changed.iter().any(|path| !path.starts_with(LEVEL_DIR)) {
+            match GameData::load() {
+                Ok(data) => {
+                    // A fixed critical asset gets the game going again
+                    if matches!(self.state, GameState::Error(_)) {
+                        self.state = GameState::Menu;
+                    }
+                    self.resources = load_resources(ctx, &data.manifest, &mut self.state);
+                    self.font = BitmapFont::new(ctx, &self.resources);
+                    self.world.reload_data(data);
+                    println!("Reloaded resources");
+                }
+                Err(err) => eprintln!("Could not reload game data, keeping the old one: {}", err),
+            }
+        }
+
+        // The level being played, and the one waiting behind the interstitial
+        let mut ids = vec![self.world.level, self.level.id];
+        ids.dedup();
+        for id in ids {
+            if !changed.contains(&level_path(id)) {
+                continue;
+            }
+            match Level::load(id) {
+                Ok(level) => {
+                    if id == self.world.level {
+                        self.world.reload_layout(&level);
+                    }
+                    if id == self.level.id {
+                        self.level = level;
+                    }
+                    println!("Reloaded level {}", id);
+                }
+                Err(err) => eprintln!("Could not reload level, keeping the old one: {}", err),
+            }
+        }
     }
 
     fn set_window_size(&mut self, ctx: &mut ggez::Context) {
         let (width, height) = self.window_sizes[self.selected_window_size];
-        self.window_width = width;
-        self.window_height = height;
-        self.block_size = width / (GRID_WIDTH as f32);
+        if self.settings.fullscreen {
+            self.toggle_fullscreen(ctx);
+        }
         ctx.gfx.set_drawable_size(width, height).unwrap();
+        self.camera = Camera::fit((width, height));
+        self.settings.window = (width, height);
+        self.save_settings();
     }
 
-    pub fn handle_collisions(&mut self) {
-        // collision Player and Enemy
-        for enemy in &mut self.enemies {
-            if
-                (self.player.pos.0 - enemy.pos.0).abs() < self.block_size &&
-                (self.player.pos.1 - enemy.pos.1).abs() < self.block_size
-            {
-                self.state = GameState::GameOver;
-            }
-        }
-
-        // collision Bullets and Enemy
-        self.bullets.retain(|bullet| {
-            let mut hit_enemy = false;
-            self.enemies.retain(|enemy| {
-                let collision =
-                    (bullet.pos.0 - enemy.pos.0).abs() < self.block_size &&
-                    (bullet.pos.1 - enemy.pos.1).abs() < self.block_size * 2.0;
-                if collision {
-                    self.score += 10;
-                    hit_enemy = true;
-                    self.bubbles.push(
-                        bubble::Bubble::new(enemy.pos, (0.0, -self.block_size / 25.0))
-                    );
+    /// Switches between the window and borderless fullscreen on the desktop.
+    fn toggle_fullscreen(&mut self, ctx: &mut ggez::Context) {
+        let mode = if self.settings.fullscreen {
+            FullscreenType::Windowed
+        } else {
+            FullscreenType::Desktop
+        };
+        match ctx.gfx.set_fullscreen(mode) {
+            Ok(()) => {
+                self.settings.fullscreen = !self.settings.fullscreen;
+                self.save_settings();
+            }
+            Err(err) => eprintln!("Could not switch fullscreen: {}", err),
+        }
+    }
+
+    fn toggle_mute(&mut self) {
+        self.settings.muted = !self.audio.is_muted();
+        self.audio.set_muted(self.settings.muted);
+        self.save_settings();
+    }
+
+    /// A key, button or stick going down: holds its actions, as `(player,
+    /// action)`, for the game and drives the menus.
+    fn input_down(
+        &mut self,
+        ctx: &mut ggez::Context,
+        direction: Option<Direction>,
+        actions: &[(usize, Action)]
+    ) {
+        for &(player, action) in actions {
+            self.actions[player].set(action, true);
+        }
+        let triggered = |action| actions.iter().any(|&(_, other)| other == action);
+
+        match self.state {
+            GameState::Menu => {
+                let item = MENU_ITEMS[self.selected_menu_option];
+                match direction {
+                    Some(Direction::Up) if self.selected_menu_option > 0 => {
+                        self.selected_menu_option -= 1;
+                    }
+                    Some(Direction::Down) if self.selected_menu_option < MENU_ITEMS.len() - 1 => {
+                        self.selected_menu_option += 1;
+                    }
+                    Some(Direction::Left | Direction::Right) if item == MenuItem::Players => {
+                        self.players = 3 - self.players;
+                    }
+                    Some(Direction::Left) if
+                        item == MenuItem::WindowSize &&
+                        self.selected_window_size > 0
+                    => {
+                        self.selected_window_size -= 1;
+                    }
+                    Some(Direction::Right) if
+                        item == MenuItem::WindowSize &&
+                        self.selected_window_size < self.window_sizes.len() - 1
+                    => {
+                        self.selected_window_size += 1;
+                    }
+                    _ if triggered(Action::Confirm) => {
+                        match item {
+                            MenuItem::Start => self.start_game(ctx),
+                            MenuItem::Players => {
+                                self.players = 3 - self.players;
+                            }
+                            MenuItem::WindowSize => self.set_window_size(ctx),
+                            MenuItem::Controls => {
+                                self.selected_control = 0;
+                                self.controls_message.clear();
+                                self.state = GameState::Controls;
+                            }
+                            MenuItem::HighScores => {
+                                self.new_high_score = None;
+                                self.state = GameState::HighScores;
+                            }
+                            MenuItem::Exit => ctx.request_quit(), // through quit_event, which saves
+                        }
+                    }
+                    _ => {}
                 }
-                !collision
-            });
-            !hit_enemy
-        });
+            }
+            GameState::Controls => {
+                // Only keys can be bound, see `key_down_event`
+                if self.rebinding.is_none() {
+                    let (confirm, back) = (triggered(Action::Confirm), triggered(Action::Back));
+                    self.controls_input(direction, confirm, back);
+                }
+            }
+            GameState::Victory | GameState::GameOver => {
+                if triggered(Action::Confirm) {
+                    self.new_high_score = None;
+                    self.enter_high_scores(ctx, 0);
+                }
+            }
+            GameState::NameEntry => {
+                let (confirm, back) = (triggered(Action::Confirm), triggered(Action::Back));
+                self.name_entry_input(ctx, direction, confirm, back);
+            }
+            GameState::HighScores => {
+                if triggered(Action::Confirm) || triggered(Action::Back) {
+                    self.state = GameState::Menu;
+                    self.reset(ctx);
+                }
+            }
+            GameState::Play => {
+                if triggered(Action::Pause) {
+                    self.pause();
+                }
+            }
+            GameState::Paused => {
+                let back = triggered(Action::Pause) || triggered(Action::Back);
+                self.pause_input(direction, triggered(Action::Confirm), back);
+            }
+            GameState::Options => {
+                let (confirm, back) = (triggered(Action::Confirm), triggered(Action::Back));
+                self.options_input(direction, confirm, back);
+            }
+            GameState::LevelTransition => {}
+            GameState::Error(_) => {
+                if triggered(Action::Back) {
+                    exit(1);
+                }
+            }
+        }
+    }
 
-        //collision enemy_bullets and player
-        for bullet in &self.enemy_bullets {
-            if
-                (bullet.pos.0 - self.player.pos.0).abs() < self.block_size &&
-                (bullet.pos.1 - self.player.pos.1).abs() < self.block_size
-            {
-                self.state = GameState::GameOver;
-            }
-        }
-
-        //collision enemy_bullet and player_bullet
-        self.bullets.retain(|bullet| {
-            let mut hit_bullet = false;
-            self.enemy_bullets.retain(|enemy_bullets| {
-                let collision =
-                    (bullet.pos.0 - enemy_bullets.pos.0).abs() < self.block_size &&
-                    (bullet.pos.1 - enemy_bullets.pos.1).abs() < self.block_size * 2.0;
-                if collision {
-                    hit_bullet = true;
+    fn input_up(&mut self, actions: &[(usize, Action)]) {
+        for &(player, action) in actions {
+            self.actions[player].set(action, false);
+        }
+    }
+
+    /// Actions of `key` for every player.
+    fn key_actions(&self, key: KeyCode) -> Vec<(usize, Action)> {
+        self.settings.bindings
+            .iter()
+            .enumerate()
+            .flat_map(|(player, bindings)| {
+                bindings.actions_for(key).map(move |action| (player, action))
+            })
+            .collect()
+    }
+
+    fn pad_input(&mut self, ctx: &mut ggez::Context, player: usize, input: PadInput) {
+        let actions: Vec<(usize, Action)> = input.actions
+            .iter()
+            .map(|&action| (player, action))
+            .collect();
+        if input.down {
+            self.input_down(ctx, input.direction, &actions);
+        } else {
+            self.input_up(&actions);
+        }
+    }
+
+    /// The gamepad with this id, known from its first event on, and the player
+    /// it plays. The first gamepad plugged in plays the first player, the next
+    /// one the second player when there are two.
+    fn pad(&mut self, id: GamepadId) -> (usize, &mut Pad) {
+        let index = match self.pads.iter().position(|(other, _)| *other == id) {
+            Some(index) => index,
+            None => {
+                self.pads.push((id, Pad::default()));
+                self.pads.len() - 1
+            }
+        };
+        let player = if index < self.players { index } else { 0 };
+        (player, &mut self.pads[index].1)
+    }
+
+    /// Notices gamepads coming and going. ggez only passes on their buttons and
+    /// axes, so this compares the connected ones every frame; an unplugged
+    /// gamepad lets go of whatever it held.
+    fn sync_gamepads(&mut self, ctx: &mut ggez::Context) {
+        let connected: Vec<(GamepadId, String)> = ctx.gamepad
+            .gamepads()
+            .map(|(id, gamepad)| (id, gamepad.name().to_string()))
+            .collect();
+        for (id, name) in &connected {
+            if !self.pads.iter().any(|(other, _)| other == id) {
+                println!("Gamepad connected: {}", name);
+                self.pads.push((*id, Pad::default()));
+            }
+        }
+
+        // Released from the players they played before the rest move up a place
+        let mut released = vec![];
+        for (index, (id, pad)) in self.pads.iter_mut().enumerate() {
+            if !connected.iter().any(|(other, _)| other == id) {
+                println!("Gamepad disconnected");
+                let player = if index < self.players { index } else { 0 };
+                for input in pad.release_all() {
+                    released.extend(input.actions.iter().map(|&action| (player, action)));
                 }
-                !collision
-            });
-            !hit_bullet
-        });
+            }
+        }
+        self.pads.retain(|(id, _)| connected.iter().any(|(other, _)| other == id));
+        self.input_up(&released);
+    }
+
+    /// Binds the pressed key while an action waits for one.
+    fn rebind(&mut self, player: usize, action: Action, keycode: KeyCode) {
+        self.controls_message = match conflict(&self.settings.bindings, player, action, keycode) {
+            Some((other_player, other)) =>
+                format!(
+                    "{} is already used for {}",
+                    key_name(keycode),
+                    control_label(other_player, other)
+                ),
+            None => {
+                self.settings.bindings[player].bind(action, keycode);
+                format!("{} set to {}", control_label(player, action), key_name(keycode))
+            }
+        };
+    }
+
+    /// Moves through the controls screen.
+    fn controls_input(&mut self, direction: Option<Direction>, confirm: bool, back: bool) {
+        let rows = control_rows();
+        let reset_row = rows.len();
+        let back_row = rows.len() + 1;
+        match direction {
+            Some(Direction::Up) if self.selected_control > 0 => {
+                self.selected_control -= 1;
+            }
+            Some(Direction::Down) if self.selected_control < back_row => {
+                self.selected_control += 1;
+            }
+            _ if back => {
+                self.leave_controls();
+            }
+            _ if confirm => {
+                if self.selected_control == reset_row {
+                    self.settings.bindings = Settings::default().bindings;
+                    self.controls_message = "Controls reset to defaults".to_string();
+                } else if self.selected_control == back_row {
+                    self.leave_controls();
+                } else {
+                    let (player, action) = rows[self.selected_control];
+                    self.rebinding = Some((player, action));
+                    self.controls_message =
+                        format!("Press a key for {}", control_label(player, action));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn leave_controls(&mut self) {
+        self.save_settings();
+        self.state = GameState::Menu;
+    }
+
+    /// Stops play where it is. The world isn't touched until play resumes.
+    fn pause(&mut self) {
+        self.audio.pause();
+        self.selected_pause_option = 0;
+        self.state = GameState::Paused;
+    }
+
+    fn resume(&mut self) {
+        // Presses made in the pause menu, like confirming with the shoot key, don't count
+        self.actions.iter_mut().for_each(ActionState::clear_presses);
+        self.audio.resume();
+        self.state = GameState::Play;
+    }
+
+    /// Starts the current level over, keeping lives and score. Recorded so the
+    /// replay restarts on the same tick.
+    fn restart_level(&mut self) {
+        self.restarts.push(self.world.tick);
+        self.world.start_level(&self.level);
+        self.actions.iter_mut().for_each(ActionState::clear);
+        self.inputs.fill(Input::default());
+        self.audio.resume();
+        self.state = GameState::Play;
+    }
+
+    /// Moves through the pause menu.
+    fn pause_input(&mut self, direction: Option<Direction>, confirm: bool, back: bool) {
+        match direction {
+            Some(Direction::Up) if self.selected_pause_option > 0 => {
+                self.selected_pause_option -= 1;
+            }
+            Some(Direction::Down) if self.selected_pause_option < PAUSE_ITEMS.len() - 1 => {
+                self.selected_pause_option += 1;
+            }
+            _ if back => self.resume(),
+            _ if confirm => {
+                match PAUSE_ITEMS[self.selected_pause_option] {
+                    PauseItem::Resume => self.resume(),
+                    PauseItem::RestartLevel => self.restart_level(),
+                    PauseItem::Options => {
+                        self.selected_option = 0;
+                        self.state = GameState::Options;
+                    }
+                    PauseItem::QuitToMenu => {
+                        self.save_replay();
+                        self.audio.resume();
+                        self.state = GameState::Menu;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves through the options screen; left and right change the volumes.
+    fn options_input(&mut self, direction: Option<Direction>, confirm: bool, back: bool) {
+        let item = OPTION_ITEMS[self.selected_option];
+        let step = match direction {
+            Some(Direction::Left) => -VOLUME_STEP,
+            Some(Direction::Right) => VOLUME_STEP,
+            _ => 0.0,
+        };
+        match direction {
+            Some(Direction::Up) if self.selected_option > 0 => {
+                self.selected_option -= 1;
+            }
+            Some(Direction::Down) if self.selected_option < OPTION_ITEMS.len() - 1 => {
+                self.selected_option += 1;
+            }
+            Some(Direction::Left | Direction::Right) if item == OptionItem::MusicVolume => {
+                self.settings.music_volume = step_volume(self.settings.music_volume, step);
+            }
+            Some(Direction::Left | Direction::Right) if item == OptionItem::SoundVolume => {
+                self.settings.sfx_volume = step_volume(self.settings.sfx_volume, step);
+            }
+            Some(Direction::Left | Direction::Right) if item == OptionItem::Mute => {
+                self.toggle_mute();
+            }
+            _ if back || (confirm && item == OptionItem::Back) => {
+                self.save_settings();
+                self.state = GameState::Paused;
+            }
+            _ if confirm && item == OptionItem::Mute => self.toggle_mute(),
+            _ => {}
+        }
+        self.audio.set_volumes(self.settings.music_volume, self.settings.sfx_volume);
+    }
+
+    fn save_settings(&self) {
+        if let Err(err) = self.settings.save() {
+            eprintln!("Could not save settings: {}", err);
+        }
     }
 }
 
 impl EventHandler for Game {
     fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        self.hot_reload(ctx);
+        self.sync_gamepads(ctx);
+
         match self.state {
             GameState::Menu => {
                 // Menülogik
             }
             GameState::Play => {
-                let delta_time = ctx.time.delta().as_secs_f32();
-                utils::update_objects(self, ctx, delta_time);
-                self.handle_collisions();
+                // Fixed timestep: ggez accumulates the frame time and hands out whole ticks
+                while
+                    self.state == GameState::Play &&
+                    ctx.time.check_update_time(TICKS_PER_SECOND)
+                {
+                    let inputs: Vec<Input> = self.actions
+                        .iter()
+                        .take(self.world.players.len())
+                        .map(ActionState::gameplay_input)
+                        .collect();
+                    for (player, &input) in inputs.iter().enumerate() {
+                        self.record(player, input);
+                    }
+                    self.world.step(FIXED_DT, &inputs);
+                    self.actions.iter_mut().for_each(ActionState::clear_presses);
+                    for &event in &self.world.events {
+                        self.audio.play(&self.resources, audio::event_sound(event));
+                    }
+
+                    if self.world.game_over {
+                        self.save_replay();
+                        self.state = GameState::GameOver;
+                    } else if self.world.is_cleared() {
+                        self.audio.play(&self.resources, audio::LEVEL_CLEAR);
+                        self.finish_level();
+                    }
+                }
+            }
+            GameState::LevelTransition => {
+                self.transition_timer -= ctx.time.delta().as_secs_f32();
+                if self.transition_timer <= 0.0 {
+                    // Keys held across the interstitial don't carry over, replays rely on it
+                    self.world.start_level(&self.level);
+                    self.actions.iter_mut().for_each(ActionState::clear);
+                    self.inputs.fill(Input::default());
+                    self.state = GameState::Play;
+                }
             }
-            GameState::GameOver => {}
+            GameState::Paused |
+            GameState::Options |
+            GameState::Victory |
+            GameState::GameOver |
+            GameState::Controls |
+            GameState::NameEntry |
+            GameState::HighScores |
+            GameState::Error(_) => {}
         }
 
-        // Framerate limitieren
-        while ctx.time.check_update_time(60) {}
+        // Drop the time accumulated outside of play so it isn't caught up later
+        while ctx.time.check_update_time(TICKS_PER_SECOND) {}
         Ok(())
     }
 
@@ -196,62 +955,204 @@ impl EventHandler for Game {
 
         match self.state {
             GameState::Menu => {
-                for (i, image) in self.resources.menu_images.clone().into_iter().enumerate() {
-                    let x = self.window_width / 2.0 - (image.width() as f32) / 2.0;
-                    let y = 150.0 + (i as f32) * 100.0;
+                let center = self.camera.screen.0 / 2.0;
+                for (i, item) in MENU_ITEMS.into_iter().enumerate() {
+                    let y = 80.0 + (i as f32) * 70.0;
                     let color = if i == self.selected_menu_option {
                         graphics::Color::WHITE // Highlighted option
                     } else {
                         graphics::Color::new(0.5, 0.5, 0.5, 1.0) // Gray for non-highlighted option
                     };
+                    let label = match item {
+                        MenuItem::Start => "START GAME",
+                        MenuItem::Players if self.players == 1 => "< 1 PLAYER >",
+                        MenuItem::Players => "< 2 PLAYERS >",
+                        MenuItem::WindowSize => "WINDOW SIZE",
+                        MenuItem::Controls => "CONTROLS",
+                        MenuItem::HighScores => "HIGH SCORES",
+                        MenuItem::Exit => "EXIT",
+                    };
+                    self.font.draw(&mut canvas, label, (center, y), Align::Center, color, 1.25);
+                }
 
-                    canvas.draw(
-                        &image,
-                        DrawParam::default().dest(ggez::mint::Point2 { x, y }).color(color)
+                if MENU_ITEMS[self.selected_menu_option] == MenuItem::WindowSize {
+                    let (width, height) = self.window_sizes[self.selected_window_size];
+                    let text = format!(
+                        "Window size: {}x{} (Left/Right to change, F11 for fullscreen)",
+                        width as u32,
+                        height as u32
                     );
+                    let color = graphics::Color::WHITE;
+                    self.font.draw(&mut canvas, &text, (center, 30.0), Align::Center, color, 0.5);
                 }
+            }
+            GameState::Controls => {
+                let mut rows: Vec<(String, String)> = control_rows()
+                    .into_iter()
+                    .map(|(player, action)| {
+                        let keys = if self.rebinding == Some((player, action)) {
+                            "...".to_string()
+                        } else {
+                            self.settings.bindings[player].describe(action)
+                        };
+                        (control_label(player, action), keys)
+                    })
+                    .collect();
+                let message_row = rows.len() + 2;
+                rows.push(("Reset to defaults".to_string(), String::new()));
+                rows.push(("Back".to_string(), String::new()));
 
-                if self.selected_menu_option == 1 {
-                    let (width, height) = self.window_sizes[self.selected_window_size];
-                    let text = graphics::Text::new(
-                        format!(
-                            "Window Size: {}x{} (Use Left/Right to change)",
-                            width as u32,
-                            height as u32
-                        )
+                // Labels and keys in two columns, the glyphs aren't all the same width
+                for (i, (label, keys)) in rows.into_iter().enumerate() {
+                    let color = if i == self.selected_control {
+                        graphics::Color::WHITE
+                    } else {
+                        graphics::Color::new(0.5, 0.5, 0.5, 1.0)
+                    };
+                    let y = 40.0 + (i as f32) * 24.0;
+                    self.font.draw(&mut canvas, &label, (100.0, y), Align::Left, color, 0.5);
+                    self.font.draw(&mut canvas, &keys, (340.0, y), Align::Left, color, 0.5);
+                }
+                self.font.draw(
+                    &mut canvas,
+                    &self.controls_message,
+                    (100.0, 50.0 + (message_row as f32) * 24.0),
+                    Align::Left,
+                    graphics::Color::WHITE,
+                    0.5
+                );
+            }
+            GameState::NameEntry => {
+                if let Some(entry) = &self.name_entry {
+                    let who = if self.world.players.len() == 1 {
+                        String::new()
+                    } else {
+                        format!("{}  ", PLAYER_NAMES[entry.player])
+                    };
+                    let title = format!(
+                        "NEW HIGH SCORE!\n\n{}{}",
+                        who,
+                        self.world.players[entry.player].score
                     );
-                    canvas.draw(
-                        &text,
-                        DrawParam::default().dest(ggez::mint::Point2 { x: 100.0, y: 300.0 })
+                    let (width, height) = self.camera.screen;
+                    let white = graphics::Color::WHITE;
+                    let pos = (width / 2.0, 60.0);
+                    self.font.draw(&mut canvas, &title, pos, Align::Center, white, 1.0);
+
+                    // The letter being picked is white, the others gray
+                    let letter_width = 48.0;
+                    let left = (width - letter_width * (entry.letters.len() as f32)) / 2.0;
+                    for (i, &letter) in entry.letters.iter().enumerate() {
+                        let color = if i == entry.cursor {
+                            graphics::Color::WHITE
+                        } else {
+                            graphics::Color::new(0.5, 0.5, 0.5, 1.0)
+                        };
+                        self.font.draw(
+                            &mut canvas,
+                            &(letter as char).to_string(),
+                            (left + ((i as f32) + 0.5) * letter_width, height / 2.0),
+                            Align::Center,
+                            color,
+                            1.5
+                        );
+                    }
+
+                    self.font.draw(
+                        &mut canvas,
+                        "Up/Down or type a letter, Left/Right to move, confirm on the last letter",
+                        (width / 2.0, height - 60.0),
+                        Align::Center,
+                        white,
+                        0.5
                     );
                 }
             }
-            GameState::Play => {
-                let _ = grid::draw(&mut canvas, self);
-                let _ = player::Player::draw(&mut canvas, self);
-                let _ = enemy::Enemy::draw(&mut canvas, self, ctx);
-                let _ = bullet::Bullet::draw(&mut canvas, self);
-                let _ = bubble::Bubble::draw(&mut canvas, self);
-                let _ = enemy_bullet::EnemyBullet::draw(&mut canvas, self);
-
-                let score_text = ggez::graphics::Text::new(format!("Score: {}", self.score));
-                canvas.draw(
-                    &score_text,
-                    DrawParam::default().dest(ggez::mint::Point2 { x: 10.0, y: 10.0 })
+            GameState::HighScores => {
+                let width = self.camera.screen.0;
+                let white = graphics::Color::WHITE;
+                let pos = (width / 2.0, 30.0);
+                self.font.draw(&mut canvas, "HIGH SCORES", pos, Align::Center, white, 1.0);
+
+                let entries = self.high_scores.entries();
+                if entries.is_empty() {
+                    let text = "No high scores yet";
+                    self.font.draw(&mut canvas, text, (100.0, 100.0), Align::Left, white, 0.6);
+                }
+                for (rank, entry) in entries.iter().enumerate() {
+                    let color = if self.new_high_score == Some(rank) {
+                        graphics::Color::YELLOW
+                    } else {
+                        graphics::Color::WHITE
+                    };
+                    // Rank, name, score, level and date in columns, numbers right aligned
+                    let y = 100.0 + (rank as f32) * 28.0;
+                    let columns = [
+                        (format!("{}.", rank + 1), 130.0, Align::Right),
+                        (entry.name.clone(), 150.0, Align::Left),
+                        (entry.score.to_string(), 360.0, Align::Right),
+                        (format!("level {}", entry.level), 400.0, Align::Left),
+                        (entry.date.clone(), 540.0, Align::Left),
+                    ];
+                    for (text, x, align) in columns {
+                        self.font.draw(&mut canvas, &text, (x, y), align, color, 0.6);
+                    }
+                }
+            }
+            GameState::Play => self.draw_play(&mut canvas, ctx)?,
+            GameState::Paused | GameState::Options => {
+                self.draw_play(&mut canvas, ctx)?;
+                self.draw_pause_menu(&mut canvas);
+            }
+            GameState::LevelTransition => {
+                let text = format!("Level {}\n\n{}", self.level.id, self.score_lines());
+                self.draw_centered(&mut canvas, &text);
+            }
+            GameState::Victory => {
+                let text = format!(
+                    "All levels cleared!\n\n{}\n\nPress {}",
+                    self.score_lines(),
+                    self.settings.bindings[0].describe(Action::Confirm)
                 );
+                self.draw_centered(&mut canvas, &text);
             }
             GameState::GameOver => {
+                if self.camera.has_playfield() {
+                    canvas.set_scissor_rect(self.camera.playfield())?;
+                    canvas.draw(
+                        self.resources.image("screen.game_over"),
+                        self.camera.sprite((-0.8, -0.8), 1.0)
+                    );
+                    canvas.set_default_scissor_rect();
+                }
+
+                let text = format!(
+                    "{}\n\nPress {}",
+                    self.score_lines(),
+                    self.settings.bindings[0].describe(Action::Confirm)
+                );
+                let (width, height) = self.camera.screen;
+                let (_, text_height) = self.font.measure(&text, 0.6);
+                self.font.draw(
+                    &mut canvas,
+                    &text,
+                    (width / 2.0, height - text_height - 20.0),
+                    Align::Center,
+                    graphics::Color::WHITE,
+                    0.6
+                );
+            }
+            GameState::Error(ref message) => {
+                let text = graphics::Text::new(
+                    format!(
+                        "The game can't start.\n\n{}\n\nPress {} to quit",
+                        message,
+                        self.settings.bindings[0].describe(Action::Back)
+                    )
+                );
                 canvas.draw(
-                    &self.resources.game_over_image,
-                    DrawParam::default()
-                        .dest(ggez::mint::Point2 {
-                            x: -self.block_size * 0.8,
-                            y: -self.block_size * 0.8,
-                        })
-                        .scale(ggez::mint::Vector2 {
-                            x: self.block_size / (GRID_WIDTH as f32) + self.block_size / 114.285,
-                            y: self.block_size / (GRID_WIDTH as f32) + self.block_size / 114.285,
-                        })
+                    &text,
+                    DrawParam::default().dest(ggez::mint::Point2 { x: 10.0, y: 10.0 })
                 );
             }
         }
@@ -264,116 +1165,140 @@ impl EventHandler for Game {
         &mut self,
         ctx: &mut ggez::Context,
         input: KeyInput,
-        _: bool
+        repeated: bool
     ) -> ggez::GameResult {
-        match self.state {
-            GameState::Menu => {
-                if let Some(keycode) = input.keycode {
-                    match keycode {
-                        KeyCode::Space => {
-                            self.reset(ctx);
-                            let _ = self.music.play(ctx);
-                            self.state = GameState::Play;
-                        }
-                        KeyCode::Up => {
-                            if self.selected_menu_option > 0 {
-                                self.selected_menu_option -= 1;
-                            }
-                        }
-                        KeyCode::Down => {
-                            if self.selected_menu_option < self.resources.menu_images.len() - 1 {
-                                self.selected_menu_option += 1;
-                            }
-                        }
-                        KeyCode::Left => {
-                            if self.selected_menu_option == 1 && self.selected_window_size > 0 {
-                                self.selected_window_size -= 1;
-                            }
-                        }
-                        KeyCode::Right => {
-                            if
-                                self.selected_menu_option == 1 &&
-                                self.selected_window_size < self.window_sizes.len() - 1
-                            {
-                                self.selected_window_size += 1;
-                            }
-                        }
-                        KeyCode::Return => {
-                            match self.selected_menu_option {
-                                0 => {
-                                    self.state = GameState::Play;
-                                }
-                                1 => {
-                                    self.set_window_size(ctx);
-                                }
-                                2 => exit(0),
-                                _ => {}
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            GameState::GameOver => {
-                if let Some(keycode) = input.keycode {
-                    match keycode {
-                        KeyCode::Space => {
-                            self.state = GameState::Menu;
-                            self.reset(ctx);
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            GameState::Play => {
-                if let Some(keycode) = input.keycode {
-                    match keycode {
-                        KeyCode::Space => {
-                            // Bullet velocity based on player facing direction
-                            let velocity = if self.player.view_right {
-                                (self.block_size / 3.0, 0.0) // Bullet moves right
-                            } else {
-                                (-self.block_size / 3.0, 0.0) // Bullet moves left
-                            };
-
-                            self.bullets.push(bullet::Bullet {
-                                pos: (self.player.pos.0, self.player.pos.1 - self.block_size * 1.1),
-                                velocity,
-                                image: self.resources.bullet_image.clone(),
-                            });
-                        }
-                        KeyCode::Left => {
-                            self.player.velocity.0 = -self.block_size / 5.0;
-                            self.player.view_right = false;
-                        }
-                        KeyCode::Right => {
-                            self.player.velocity.0 = self.block_size / 5.0;
-                            self.player.view_right = true;
-                        }
-                        KeyCode::Up => {
-                            if self.player.velocity.1 == 0.0 {
-                                self.player.velocity.1 = -self.block_size / 2.4;
-                            }
-                        }
-                        _ => {}
-                    }
+        let Some(keycode) = input.keycode else {
+            return Ok(());
+        };
+        // Held keys repeat; only the first press counts
+        if repeated {
+            return Ok(());
+        }
+        if keycode == KeyCode::F11 {
+            self.toggle_fullscreen(ctx);
+            return Ok(());
+        }
+        if keycode == KeyCode::F10 {
+            self.toggle_mute();
+            return Ok(());
+        }
+        if let Some((player, action)) = self.rebinding.take() {
+            self.rebind(player, action, keycode);
+            return Ok(());
+        }
+        if let Some(entry) = &mut self.name_entry {
+            let name = key_name(keycode);
+            if let [letter] = name.as_bytes() {
+                if letter.is_ascii_uppercase() {
+                    entry.type_letter(*letter as char);
+                    return Ok(());
                 }
             }
         }
+        let actions = self.key_actions(keycode);
+        self.input_down(ctx, Direction::from_arrow(keycode), &actions);
         Ok(())
     }
 
     fn key_up_event(&mut self, _: &mut ggez::Context, input: KeyInput) -> ggez::GameResult {
         if let Some(keycode) = input.keycode {
-            if self.state == GameState::Play {
-                if
-                    (keycode == KeyCode::Left && self.player.velocity.0 < 0.0) ||
-                    (keycode == KeyCode::Right && self.player.velocity.0 > 0.0)
-                {
-                    self.player.velocity.0 = 0.0;
-                }
-            }
+            let actions = self.key_actions(keycode);
+            self.input_up(&actions);
+        }
+        Ok(())
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        button: PadButton,
+        id: GamepadId
+    ) -> ggez::GameResult {
+        let (player, pad) = self.pad(id);
+        if let Some(input) = pad.button(button, true) {
+            self.pad_input(ctx, player, input);
+        }
+        Ok(())
+    }
+
+    fn gamepad_button_up_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        button: PadButton,
+        id: GamepadId
+    ) -> ggez::GameResult {
+        let (player, pad) = self.pad(id);
+        if let Some(input) = pad.button(button, false) {
+            self.pad_input(ctx, player, input);
         }
         Ok(())
     }
+
+    fn gamepad_axis_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        axis: Axis,
+        value: f32,
+        id: GamepadId
+    ) -> ggez::GameResult {
+        let (player, pad) = self.pad(id);
+        for input in pad.axis(axis, value) {
+            self.pad_input(ctx, player, input);
+        }
+        Ok(())
+    }
+
+    /// Resizing the window (or going fullscreen) keeps the playfield's aspect
+    /// ratio and adds bars instead.
+    /// Sizes below `MIN_SCREEN`, like a minimised window's, are ignored.
+    fn resize_event(&mut self, _: &mut ggez::Context, width: f32, height: f32) -> ggez::GameResult {
+        if width < MIN_SCREEN.0 || height < MIN_SCREEN.1 {
+            return Ok(());
+        }
+        self.camera = Camera::fit((width, height));
+        // Saved on quit rather than for every step of dragging the window edge
+        if !self.settings.fullscreen {
+            self.settings.window = (width, height);
+        }
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _: &mut ggez::Context) -> ggez::GameResult<bool> {
+        self.save_settings();
+        if matches!(self.state, GameState::Play | GameState::Paused | GameState::Options) {
+            self.save_replay();
+        }
+        Ok(false)
+    }
+
+    /// Losing the window pauses play. Keys let go of meanwhile would never be
+    /// seen released, so none stay held.
+    fn focus_event(&mut self, _: &mut ggez::Context, gained: bool) -> ggez::GameResult {
+        if !gained && self.state == GameState::Play {
+            self.actions.iter_mut().for_each(ActionState::clear);
+            self.pause();
+        }
+        Ok(())
+    }
+}
+
+/// `volume` changed by `step`, kept within 0 and 1 and on whole steps.
+fn step_volume(volume: f32, step: f32) -> f32 {
+    ((volume + step).clamp(0.0, 1.0) / VOLUME_STEP).round() * VOLUME_STEP
+}
+
+/// Loads the resources, switching to the error screen if a critical asset is
+/// broken. Everything else broken is only reported.
+fn load_resources(
+    ctx: &ggez::Context,
+    manifest: &AssetManifest,
+    state: &mut GameState
+) -> Resources {
+    Resources::load(ctx, manifest).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        if err.is_critical() {
+            *state = GameState::Error(err.to_string());
+        }
+        err.into_resources()
+    })
 }
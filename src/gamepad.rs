@@ -0,0 +1,197 @@
+use ggez::event::{ Axis, Button };
+
+use crate::actions::{ Action, Direction };
+
+/// How far a stick has to be pushed, out of 1, before it counts. Keeps worn
+/// sticks that don't quite center from walking the player around.
+pub const DEAD_ZONE: f32 = 0.35;
+
+/// A button or stick of a gamepad going down or up, handled like a key.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PadInput {
+    pub actions: &'static [Action],
+    pub direction: Option<Direction>,
+    pub down: bool,
+}
+
+/// What a button does, in game and in menus. Fixed, unlike the keys: face
+/// buttons laid out like most games, the d-pad like the arrow keys.
+fn button_input(button: Button) -> Option<(&'static [Action], Option<Direction>)> {
+    let input: (&'static [Action], Option<Direction>) = match button {
+        Button::South => (&[Action::Jump, Action::Confirm], None),
+        Button::North => (&[Action::Jump], None),
+        Button::West => (&[Action::Shoot], None),
+        Button::East => (&[Action::Shoot, Action::Back], None),
+        Button::Start => (&[Action::Pause, Action::Confirm], None),
+        Button::Select => (&[Action::Back], None),
+        Button::DPadUp => (&[], Some(Direction::Up)),
+        Button::DPadDown => (&[], Some(Direction::Down)),
+        Button::DPadLeft => (&[Action::MoveLeft], Some(Direction::Left)),
+        Button::DPadRight => (&[Action::MoveRight], Some(Direction::Right)),
+        _ => {
+            return None;
+        }
+    };
+    Some(input)
+}
+
+/// Pushing the left stick or an analog d-pad acts like the d-pad buttons.
+fn direction_actions(direction: Direction) -> &'static [Action] {
+    match direction {
+        Direction::Left => &[Action::MoveLeft],
+        Direction::Right => &[Action::MoveRight],
+        Direction::Up | Direction::Down => &[],
+    }
+}
+
+/// The direction an axis is pushed in, none inside the dead zone.
+fn axis_direction(axis: Axis, value: f32) -> Option<Direction> {
+    let horizontal = match axis {
+        Axis::LeftStickX | Axis::DPadX => true,
+        Axis::LeftStickY | Axis::DPadY => false,
+        _ => {
+            return None;
+        }
+    };
+    if value.abs() < DEAD_ZONE {
+        return None;
+    }
+    // Up is positive on gamepads
+    Some(match (horizontal, value > 0.0) {
+        (true, false) => Direction::Left,
+        (true, true) => Direction::Right,
+        (false, true) => Direction::Up,
+        (false, false) => Direction::Down,
+    })
+}
+
+/// What one gamepad holds down, so its events turn into clean presses and
+/// releases and everything is let go when it is unplugged.
+#[derive(Default)]
+pub struct Pad {
+    buttons: Vec<Button>,
+    axes: Vec<(Axis, Direction)>,
+}
+
+impl Pad {
+    pub fn button(&mut self, button: Button, down: bool) -> Option<PadInput> {
+        let (actions, direction) = button_input(button)?;
+        let held = self.buttons.contains(&button);
+        if down == held {
+            return None;
+        }
+        if down {
+            self.buttons.push(button);
+        } else {
+            self.buttons.retain(|&other| other != button);
+        }
+        Some(PadInput { actions, direction, down })
+    }
+
+    /// A new axis position, releasing the direction it left and pressing the
+    /// one it entered.
+    pub fn axis(&mut self, axis: Axis, value: f32) -> Vec<PadInput> {
+        let direction = axis_direction(axis, value);
+        let held = self.axes
+            .iter()
+            .find(|(other, _)| *other == axis)
+            .map(|&(_, direction)| direction);
+        if direction == held {
+            return vec![];
+        }
+
+        let mut inputs = vec![];
+        if let Some(held) = held {
+            self.axes.retain(|(other, _)| *other != axis);
+            inputs.push(PadInput {
+                actions: direction_actions(held),
+                direction: Some(held),
+                down: false,
+            });
+        }
+        if let Some(direction) = direction {
+            self.axes.push((axis, direction));
+            inputs.push(PadInput {
+                actions: direction_actions(direction),
+                direction: Some(direction),
+                down: true,
+            });
+        }
+        inputs
+    }
+
+    /// Lets go of everything, for when the gamepad goes away.
+    pub fn release_all(&mut self) -> Vec<PadInput> {
+        let mut inputs: Vec<PadInput> = std::mem::take(&mut self.buttons)
+            .into_iter()
+            .filter_map(|button| button_input(button))
+            .map(|(actions, direction)| PadInput { actions, direction, down: false })
+            .collect();
+        for (_, direction) in std::mem::take(&mut self.axes) {
+            inputs.push(PadInput {
+                actions: direction_actions(direction),
+                direction: Some(direction),
+                down: false,
+            });
+        }
+        inputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(direction: Direction, down: bool) -> PadInput {
+        PadInput { actions: direction_actions(direction), direction: Some(direction), down }
+    }
+
+    #[test]
+    fn sticks_inside_the_dead_zone_do_nothing() {
+        let mut pad = Pad::default();
+        assert_eq!(pad.axis(Axis::LeftStickX, 0.2), []);
+        assert_eq!(pad.axis(Axis::LeftStickX, -DEAD_ZONE + 0.01), []);
+        assert_eq!(pad.axis(Axis::LeftStickX, 0.9), [input(Direction::Right, true)]);
+        assert_eq!(pad.axis(Axis::LeftStickX, 0.6), []); // still pushed
+        assert_eq!(pad.axis(Axis::LeftStickX, 0.1), [input(Direction::Right, false)]);
+        assert_eq!(pad.axis(Axis::LeftStickY, 0.8), [input(Direction::Up, true)]);
+        assert_eq!(pad.axis(Axis::RightStickX, 1.0), []);
+    }
+
+    #[test]
+    fn flicking_a_stick_across_releases_before_pressing() {
+        let mut pad = Pad::default();
+        pad.axis(Axis::LeftStickX, -1.0);
+        assert_eq!(pad.axis(Axis::LeftStickX, 1.0), [
+            input(Direction::Left, false),
+            input(Direction::Right, true),
+        ]);
+    }
+
+    #[test]
+    fn repeated_button_events_count_once() {
+        let mut pad = Pad::default();
+        let press = pad.button(Button::South, true).unwrap();
+        assert_eq!(press.actions, [Action::Jump, Action::Confirm]);
+        assert!(press.down);
+        assert_eq!(pad.button(Button::South, true), None);
+        assert!(pad.button(Button::South, false).is_some());
+        assert_eq!(pad.button(Button::South, false), None);
+        assert_eq!(pad.button(Button::LeftTrigger2, true), None); // not mapped
+    }
+
+    #[test]
+    fn release_all_lets_go_of_buttons_and_sticks() {
+        let mut pad = Pad::default();
+        pad.button(Button::West, true);
+        pad.button(Button::DPadLeft, true);
+        pad.axis(Axis::LeftStickY, -1.0);
+
+        let released = pad.release_all();
+        assert_eq!(released.len(), 3);
+        assert!(released.iter().all(|input| !input.down));
+        assert!(released.contains(&input(Direction::Down, false)));
+        assert!(released.iter().any(|input| input.actions == [Action::Shoot]));
+        assert_eq!(pad.release_all(), []);
+    }
+}
@@ -1,30 +1,16 @@
 use ggez::graphics::DrawParam;
 use crate::game::Game;
+use crate::level::{ Cell, Level };
 
 pub const GRID_WIDTH: usize = 32;
 pub const GRID_HEIGHT: usize = 18;
-// pub const BLOCK_SIZE: f32 = 25.0;
 
-#[derive(Clone)]
-pub struct GridConfig {
-    pub vertical_lines: Vec<(usize, usize, usize)>, // (start_x, start_y, end_y)
-    pub horizontal_lines: Vec<(usize, usize, usize)>, // (y,start_x, end_x)
-}
-
-pub fn create_grid(config: &GridConfig) -> [[bool; GRID_WIDTH]; GRID_HEIGHT] {
+pub fn create_grid(level: &Level) -> [[bool; GRID_WIDTH]; GRID_HEIGHT] {
     let mut grid = [[false; GRID_WIDTH]; GRID_HEIGHT];
 
-    // Vertical lines
-    for &(start_x, start_y, end_y) in &config.vertical_lines {
-        for y in start_y..end_y {
-            grid[y][start_x] = true;
-        }
-    }
-
-    // Horizontal lines
-    for &(y, start_x, end_x) in &config.horizontal_lines {
-        for x in start_x..end_x {
-            grid[y][x] = true;
+    for (row, cells) in grid.iter_mut().zip(level.cells.iter()) {
+        for (solid, &cell) in row.iter_mut().zip(cells.iter()) {
+            *solid = cell == Cell::Block;
         }
     }
 
@@ -35,21 +21,30 @@ pub fn draw(
     canvas: &mut ggez::graphics::Canvas,
     game: &mut Game
 ) -> Result<(), Box<dyn std::error::Error>> {
-    for y in 0..GRID_HEIGHT {
-        for x in 0..GRID_WIDTH {
-            if game.grid[y][x] {
-                canvas.draw(
-                    &game.resources.grid_image,
-                    DrawParam::default()
-                        .dest(ggez::mint::Point2 {
-                            x: (x as f32) * game.block_size,
-                            y: (y as f32) * game.block_size,
-                        })
-                        .scale(ggez::mint::Vector2 {
-                            x: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285, // + 0.21875 = (1-block_size/grid-width)
-                            y: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285,
-                        })
-                );
+    let world = &game.world;
+    let resources = &game.resources;
+    let camera = &game.camera;
+
+    // Every level cycles through the block and background sets
+    let backgrounds = resources.images("level.backgrounds");
+    let blocks = resources.images("level.blocks");
+    let background = backgrounds[(world.level - 1) % backgrounds.len()];
+    let block_image = blocks[(world.level - 1) % blocks.len()];
+    // The background is stretched over the whole playfield
+    canvas.draw(
+        background,
+        DrawParam::default()
+            .dest(camera.to_screen((0.0, 0.0)))
+            .scale(ggez::mint::Vector2 {
+                x: ((GRID_WIDTH as f32) * camera.scale) / (background.width() as f32),
+                y: ((GRID_HEIGHT as f32) * camera.scale) / (background.height() as f32),
+            })
+    );
+
+    for (y, row) in world.grid.iter().enumerate() {
+        for (x, &solid) in row.iter().enumerate() {
+            if solid {
+                canvas.draw(block_image, camera.sprite((x as f32, y as f32), 1.0));
             }
         }
     }
@@ -0,0 +1,322 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use crate::settings::config_dir;
+
+/// The best runs, kept in `highscores.txt` next to the settings:
+///
+///     bobble-highscores <version>
+///     checksum <hex>
+///     entry <score> <level> <date> <name>
+///
+/// The checksum covers everything after its own line, so a hand-edited or cut
+/// off file is rejected as a whole rather than read halfway.
+pub const HIGH_SCORES_FILE: &str = "highscores.txt";
+const HIGH_SCORES_VERSION: u32 = 1;
+const MAGIC: &str = "bobble-highscores";
+/// Entries kept in the table.
+pub const MAX_ENTRIES: usize = 10;
+/// Letters of a name, like on arcade machines.
+pub const NAME_LENGTH: usize = 3;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub score: i32,
+    pub level: usize, // reached
+    pub date: String, // YYYY-MM-DD
+}
+
+/// Best first, at most `MAX_ENTRIES`.
+#[derive(Default)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+#[derive(Debug)]
+pub enum HighScoreError {
+    NoConfigDir,
+    Io(PathBuf, io::Error),
+    Version(u32),
+    Checksum,
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for HighScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HighScoreError::NoConfigDir => write!(f, "no user config directory"),
+            HighScoreError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            HighScoreError::Version(version) =>
+                write!(
+                    f,
+                    "version {} is not supported (expected {})",
+                    version,
+                    HIGH_SCORES_VERSION
+                ),
+            HighScoreError::Checksum => write!(f, "checksum mismatch, the file was changed"),
+            HighScoreError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+pub fn high_scores_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(HIGH_SCORES_FILE))
+}
+
+impl HighScores {
+    pub fn entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    /// Whether `score` makes it into the table.
+    pub fn qualifies(&self, score: i32) -> bool {
+        score > 0 &&
+            (self.entries.len() < MAX_ENTRIES ||
+                self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Puts `entry` in its place, below equal scores already there, and returns
+    /// its rank counting from 0. The lowest entry drops out of a full table.
+    pub fn insert(&mut self, entry: HighScore) -> usize {
+        let rank = self.entries
+            .iter()
+            .position(|other| other.score < entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        rank
+    }
+
+    /// Reads the table; a missing file means an empty one.
+    pub fn load() -> Result<Self, HighScoreError> {
+        let path = high_scores_path().ok_or(HighScoreError::NoConfigDir)?;
+        match fs::read_to_string(&path) {
+            Ok(text) => HighScores::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HighScores::default()),
+            Err(err) => Err(HighScoreError::Io(path, err)),
+        }
+    }
+
+    pub fn save(&self) -> Result<PathBuf, HighScoreError> {
+        let path = high_scores_path().ok_or(HighScoreError::NoConfigDir)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| HighScoreError::Io(dir.to_path_buf(), err))?;
+        }
+        fs::write(&path, self.to_text()).map_err(|err| HighScoreError::Io(path.clone(), err))?;
+        Ok(path)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut body = String::new();
+        for entry in &self.entries {
+            body += &format!(
+                "entry {} {} {} {}\n",
+                entry.score,
+                entry.level,
+                entry.date,
+                entry.name
+            );
+        }
+        format!("{} {}\nchecksum {:016x}\n{}", MAGIC, HIGH_SCORES_VERSION, checksum(&body), body)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, HighScoreError> {
+        let error = |line: usize, message: &str| HighScoreError::Parse {
+            line,
+            message: message.to_string(),
+        };
+
+        let mut lines = text.splitn(3, '\n');
+        let header = lines.next().unwrap_or("");
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [MAGIC, version] => version.parse::<u32>().map_err(|_| error(1, "bad version"))?,
+            _ => {
+                return Err(error(1, "not a high score file"));
+            }
+        };
+        if version != HIGH_SCORES_VERSION {
+            return Err(HighScoreError::Version(version));
+        }
+        let expected = match lines.next().map(|line| line.split_whitespace().collect::<Vec<_>>()) {
+            Some(fields) if fields.len() == 2 && fields[0] == "checksum" =>
+                u64::from_str_radix(fields[1], 16).map_err(|_| error(2, "bad checksum"))?,
+            _ => {
+                return Err(error(2, "expected 'checksum <hex>'"));
+            }
+        };
+        let body = lines.next().unwrap_or("");
+        if checksum(body) != expected {
+            return Err(HighScoreError::Checksum);
+        }
+
+        let mut high_scores = HighScores::default();
+        for (index, line) in body.lines().enumerate() {
+            let number = index + 3;
+            let entry = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["entry", score, level, date, name] =>
+                    HighScore {
+                        score: score.parse().map_err(|_| error(number, "bad score"))?,
+                        level: level.parse().map_err(|_| error(number, "bad level"))?,
+                        date: date.to_string(),
+                        name: Some(name)
+                            .filter(|name| is_valid_name(name))
+                            .ok_or_else(|| error(number, "bad name"))?
+                            .to_string(),
+                    },
+                _ => {
+                    return Err(error(number, "expected 'entry <score> <level> <date> <name>'"));
+                }
+            };
+            if high_scores.entries.last().is_some_and(|last| last.score < entry.score) {
+                return Err(error(number, "entries are not sorted by score"));
+            }
+            if high_scores.entries.len() == MAX_ENTRIES {
+                return Err(error(number, &format!("more than {} entries", MAX_ENTRIES)));
+            }
+            high_scores.entries.push(entry);
+        }
+        Ok(high_scores)
+    }
+}
+
+/// `NAME_LENGTH` letters from A to Z.
+pub fn is_valid_name(name: &str) -> bool {
+    name.len() == NAME_LENGTH && name.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// FNV-1a; enough to notice edits and truncation, not meant to stop cheats.
+fn checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ (byte as u64)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86400)
+        .unwrap_or(0) as i64;
+    // Days since 1970 to the civil calendar, after Howard Hinnant's `civil_from_days`
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (if month <= 2 { 1 } else { 0 });
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// An arcade-style name being entered: letters are picked one at a time by
+/// stepping through the alphabet, or typed.
+pub struct NameEntry {
+    pub player: usize, // whose score it is
+    pub letters: [u8; NAME_LENGTH],
+    pub cursor: usize,
+}
+
+impl NameEntry {
+    pub fn new(player: usize) -> Self {
+        NameEntry { player, letters: [b'A'; NAME_LENGTH], cursor: 0 }
+    }
+
+    /// Steps the letter under the cursor through the alphabet, wrapping around.
+    pub fn step_letter(&mut self, delta: i8) {
+        let letter = &mut self.letters[self.cursor];
+        *letter = b'A' + ((*letter - b'A') as i8 + delta).rem_euclid(26) as u8;
+    }
+
+    /// Puts `letter` under the cursor and moves on.
+    pub fn type_letter(&mut self, letter: char) {
+        if letter.is_ascii_uppercase() {
+            self.letters[self.cursor] = letter as u8;
+            self.next();
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.cursor = (self.cursor + 1).min(NAME_LENGTH - 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn is_on_last_letter(&self) -> bool {
+        self.cursor == NAME_LENGTH - 1
+    }
+
+    pub fn name(&self) -> String {
+        self.letters.iter().map(|&letter| letter as char).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: i32) -> HighScore {
+        HighScore { name: name.to_string(), score, level: 2, date: "2026-10-18".to_string() }
+    }
+
+    fn table() -> HighScores {
+        let mut high_scores = HighScores::default();
+        for (name, score) in [("BUB", 3000), ("BOB", 1200), ("ABC", 1200)] {
+            high_scores.insert(entry(name, score));
+        }
+        high_scores
+    }
+
+    #[test]
+    fn saved_table_parses_back_unchanged() {
+        let high_scores = table();
+        let parsed = HighScores::parse(&high_scores.to_text()).unwrap();
+        assert_eq!(parsed.entries(), high_scores.entries());
+        assert!(HighScores::parse(&HighScores::default().to_text()).unwrap().entries().is_empty());
+    }
+
+    #[test]
+    fn edited_or_truncated_files_fail_the_checksum() {
+        let text = table().to_text();
+        let edited = text.replace("entry 1200", "entry 9200");
+        assert!(matches!(HighScores::parse(&edited), Err(HighScoreError::Checksum)));
+
+        let truncated = &text[..text.len() - 10];
+        assert!(matches!(HighScores::parse(truncated), Err(HighScoreError::Checksum)));
+
+        let older = text.replacen(&HIGH_SCORES_VERSION.to_string(), "0", 1);
+        assert!(matches!(HighScores::parse(&older), Err(HighScoreError::Version(0))));
+    }
+
+    #[test]
+    fn insert_keeps_the_best_entries_in_order() {
+        let mut high_scores = table();
+        assert_eq!(high_scores.entries()[1].name, "BOB"); // ties go below
+        assert_eq!(high_scores.entries()[2].name, "ABC");
+
+        for score in 1..=MAX_ENTRIES as i32 {
+            high_scores.insert(entry("AAA", score * 100));
+        }
+        let scores: Vec<i32> = high_scores.entries().iter().map(|entry| entry.score).collect();
+        assert_eq!(scores.len(), MAX_ENTRIES);
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_eq!(*scores.last().unwrap(), 400);
+
+        assert!(!high_scores.qualifies(400));
+        assert!(high_scores.qualifies(401));
+        assert_eq!(high_scores.insert(entry("ZZZ", 401)), MAX_ENTRIES - 1);
+        assert_eq!(high_scores.entries().len(), MAX_ENTRIES);
+        assert!(!HighScores::default().qualifies(0));
+    }
+}
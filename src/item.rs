@@ -0,0 +1,82 @@
+use crate::{
+    animation::Animator,
+    camera::Camera,
+    data::GameData,
+    game::Game,
+    grid::{ GRID_HEIGHT, GRID_WIDTH },
+    utils::{ check_collision, GRAVITY },
+};
+
+/// Seconds a fruit lies around before it disappears.
+const ITEM_LIFETIME: f32 = 10.0;
+/// The fruit blinks during its last seconds, this long on and off.
+const BLINK_TIME: f32 = 2.0;
+const BLINK_INTERVAL: f32 = 0.1;
+
+/// A fruit left behind by a popped bubble.
+pub struct Item {
+    pub pos: (f32, f32), // bottom center
+    pub animator: Animator,
+    value: i32,
+    velocity_y: f32,
+    lifetime: f32,
+}
+
+impl Item {
+    /// The `fruit.*` assets go from the smallest fruit to the biggest. Combos
+    /// hand out bigger fruit, the biggest one for any longer combo.
+    pub fn new(pos: (f32, f32), combo: usize, data: &GameData) -> Self {
+        let kind = combo.min(data.fruit_scores.len() - 1);
+        let fruit = data.manifest.group("fruit").nth(kind).map_or("", |fruit| &fruit.name);
+        Item {
+            pos,
+            animator: Animator::new(data.clips.get(fruit)),
+            value: data.fruit_scores[kind],
+            velocity_y: 0.0,
+            lifetime: ITEM_LIFETIME,
+        }
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    pub fn update(
+        &mut self,
+        grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT],
+        dt: f32
+    ) {
+        self.lifetime -= dt;
+        self.animator.update(dt);
+
+        // Fall until the fruit lands on a block
+        self.velocity_y += GRAVITY * dt;
+        let next_y = self.pos.1 + self.velocity_y * dt;
+        if self.velocity_y > 0.0 && check_collision(grid, self.pos.0, next_y) {
+            self.pos.1 = next_y.floor();
+            self.velocity_y = 0.0;
+        } else {
+            self.pos.1 = next_y;
+        }
+        if self.pos.1 > (GRID_HEIGHT as f32) {
+            self.pos.1 = 0.0; // falls through the floor and comes in from the top
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.lifetime <= 0.0
+    }
+
+    pub fn draw(canvas: &mut ggez::graphics::Canvas, game: &mut Game) {
+        let world = &game.world;
+        for item in &world.items {
+            if item.lifetime < BLINK_TIME && ((item.lifetime / BLINK_INTERVAL) as i32) % 2 == 0 {
+                continue;
+            }
+            let image = game.resources.frame(item.animator.frame());
+            let size = Camera::tiles(image);
+            let pos = (item.pos.0 - size.0 / 2.0, item.pos.1 - size.1);
+            canvas.draw(image, game.camera.sprite(pos, 1.0));
+        }
+    }
+}
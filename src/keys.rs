@@ -0,0 +1,32 @@
+use ggez::input::keyboard::KeyCode;
+
+/// Keys that can be bound to something, named in files like ggez names them
+/// (`Left`, `Space`, `A`, `Key1`, `Numpad0`, ...).
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+    KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Key0, KeyCode::A, KeyCode::B, KeyCode::C,
+    KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K,
+    KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S,
+    KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Left, KeyCode::Up, KeyCode::Right, KeyCode::Down, KeyCode::Space, KeyCode::Return,
+    KeyCode::Tab, KeyCode::Back, KeyCode::Escape, KeyCode::Insert, KeyCode::Home, KeyCode::Delete,
+    KeyCode::End, KeyCode::PageUp, KeyCode::PageDown, KeyCode::F1, KeyCode::F2, KeyCode::F3,
+    KeyCode::F4, KeyCode::F5, KeyCode::F6, KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10,
+    KeyCode::F11, KeyCode::F12, KeyCode::LShift, KeyCode::RShift, KeyCode::LControl,
+    KeyCode::RControl, KeyCode::LAlt, KeyCode::RAlt, KeyCode::Numpad0, KeyCode::Numpad1,
+    KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4, KeyCode::Numpad5, KeyCode::Numpad6,
+    KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9, KeyCode::NumpadAdd,
+    KeyCode::NumpadSubtract, KeyCode::NumpadMultiply, KeyCode::NumpadDivide,
+    KeyCode::NumpadDecimal, KeyCode::NumpadEnter, KeyCode::Comma, KeyCode::Period, KeyCode::Slash,
+    KeyCode::Backslash, KeyCode::Semicolon, KeyCode::Apostrophe, KeyCode::Minus, KeyCode::Equals,
+    KeyCode::LBracket, KeyCode::RBracket, KeyCode::Grave,
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// The bindable key with this name.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|&key| key_name(key) == name)
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::grid::{ GRID_HEIGHT, GRID_WIDTH };
+
+/// Level maps live here as `levelNN.txt`, one character per grid cell:
+///
+/// * `#` block, `.` or space empty
+/// * `@` player spawn (exactly one), `&` spawn of the second player (at most
+///   one, the second player shares `@` without it)
+/// * `a`-`z` enemy spawn of that type walking left, `A`-`Z` walking right
+/// * `^` `<` `>` `_` air current pushing bubbles up, left, right or down; bubbles
+///   float up wherever no current is marked
+///
+/// Lines starting with `;` are comments and don't count as map rows.
+pub const LEVEL_DIR: &str = "resources/levels";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Cell {
+    Empty,
+    Block,
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Current {
+    #[default]
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Current {
+    pub fn direction(self) -> (f32, f32) {
+        match self {
+            Current::Up => (0.0, -1.0),
+            Current::Down => (0.0, 1.0),
+            Current::Left => (-1.0, 0.0),
+            Current::Right => (1.0, 0.0),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EnemySpawn {
+    pub cell: (usize, usize),
+    pub kind: char,
+    pub facing_right: bool,
+}
+
+#[derive(Clone)]
+pub struct Level {
+    pub id: usize,
+    pub cells: [[Cell; GRID_WIDTH]; GRID_HEIGHT],
+    pub currents: [[Current; GRID_WIDTH]; GRID_HEIGHT],
+    pub player_spawns: [(usize, usize); 2],
+    pub enemy_spawns: Vec<EnemySpawn>,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(PathBuf, std::io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            LevelError::Parse { path, line, column, message } =>
+                write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+        }
+    }
+}
+
+pub fn level_path(id: usize) -> PathBuf {
+    PathBuf::from(LEVEL_DIR).join(format!("level{:02}.txt", id))
+}
+
+impl Level {
+    pub fn load(id: usize) -> Result<Self, LevelError> {
+        let path = level_path(id);
+        let text = fs::read_to_string(&path).map_err(|err| LevelError::Io(path.clone(), err))?;
+        Level::parse(id, &text).map_err(|(line, column, message)| LevelError::Parse {
+            path,
+            line,
+            column,
+            message,
+        })
+    }
+
+    /// A map without blocks or spawns, standing in for one that couldn't be loaded.
+    pub fn empty(id: usize) -> Self {
+        Level {
+            id,
+            cells: [[Cell::Empty; GRID_WIDTH]; GRID_HEIGHT],
+            currents: [[Current::Up; GRID_WIDTH]; GRID_HEIGHT],
+            player_spawns: [(0, 0); 2],
+            enemy_spawns: vec![],
+        }
+    }
+
+    /// Loads the level after `id`, or `None` when there is no such file, i.e. `id`
+    /// was the last level.
+    pub fn load_next(id: usize) -> Result<Option<Self>, LevelError> {
+        if !level_path(id + 1).exists() {
+            return Ok(None);
+        }
+        Level::load(id + 1).map(Some)
+    }
+
+    /// Parses a map, reporting problems as 1-based `(line, column, message)`.
+    pub fn parse(id: usize, text: &str) -> Result<Self, (usize, usize, String)> {
+        let mut cells = [[Cell::Empty; GRID_WIDTH]; GRID_HEIGHT];
+        let mut currents = [[Current::Up; GRID_WIDTH]; GRID_HEIGHT];
+        let mut player_spawn = None;
+        let mut second_spawn = None;
+        let mut enemy_spawns = vec![];
+        let mut y = 0;
+        let mut last_line = 0;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            last_line = line_number;
+            if line.starts_with(';') {
+                continue;
+            }
+            if y == GRID_HEIGHT {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Err((line_number, 1, format!("map has more than {} rows", GRID_HEIGHT)));
+            }
+
+            let row: Vec<char> = line.trim_end_matches('\r').chars().collect();
+            if row.len() != GRID_WIDTH {
+                let column = row.len().min(GRID_WIDTH) + 1;
+                return Err((
+                    line_number,
+                    column,
+                    format!("row has {} cells, expected {}", row.len(), GRID_WIDTH),
+                ));
+            }
+
+            for (x, &marker) in row.iter().enumerate() {
+                match marker {
+                    '#' => {
+                        cells[y][x] = Cell::Block;
+                    }
+                    '.' | ' ' | '^' => {}
+                    '<' => {
+                        currents[y][x] = Current::Left;
+                    }
+                    '>' => {
+                        currents[y][x] = Current::Right;
+                    }
+                    '_' => {
+                        currents[y][x] = Current::Down;
+                    }
+                    '@' => {
+                        if player_spawn.is_some() {
+                            return Err((line_number, x + 1, "second player spawn".to_string()));
+                        }
+                        player_spawn = Some((x, y));
+                    }
+                    '&' => {
+                        if second_spawn.is_some() {
+                            return Err((
+                                line_number,
+                                x + 1,
+                                "second spawn of the second player".to_string(),
+                            ));
+                        }
+                        second_spawn = Some((x, y));
+                    }
+                    'a'..='z' | 'A'..='Z' => {
+                        enemy_spawns.push(EnemySpawn {
+                            cell: (x, y),
+                            kind: marker.to_ascii_lowercase(),
+                            facing_right: marker.is_ascii_uppercase(),
+                        });
+                    }
+                    _ => {
+                        return Err((line_number, x + 1, format!("unknown cell '{}'", marker)));
+                    }
+                }
+            }
+            y += 1;
+        }
+
+        if y < GRID_HEIGHT {
+            return Err((
+                last_line + 1,
+                1,
+                format!("map has {} rows, expected {}", y, GRID_HEIGHT),
+            ));
+        }
+        let player_spawn = player_spawn.ok_or((1, 1, "no player spawn '@'".to_string()))?;
+
+        Ok(Level {
+            id,
+            cells,
+            currents,
+            player_spawns: [player_spawn, second_spawn.unwrap_or(player_spawn)],
+            enemy_spawns,
+        })
+    }
+}
@@ -1,24 +1,71 @@
+use std::process::exit;
+
 use ggez::{ ContextBuilder, GameResult };
 use ggez::event::{ self };
-use ggez::conf::WindowMode;
+use ggez::conf::{ FullscreenType, WindowMode };
 
+mod actions;
+mod animation;
+mod camera;
 mod game;
 mod player;
 mod enemy;
+mod enemy_type;
 mod grid;
 mod utils;
 mod bullet;
 mod resources;
 mod bubble;
 mod enemy_bullet;
+mod world;
+mod replay;
+mod level;
+mod nav;
+mod item;
+mod assets;
+mod data;
+mod watcher;
+mod keys;
+mod gamepad;
+mod font;
+mod audio;
+mod settings;
+mod highscores;
 
 fn main() -> GameResult {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--replay") {
+        let Some(path) = args.get(i + 1) else {
+            eprintln!("Usage: --replay <file>");
+            exit(2);
+        };
+        exit(replay::run_headless(path));
+    }
+
+    let settings = settings::Settings::load().unwrap_or_else(|err| {
+        eprintln!("Could not read settings, using the defaults: {}", err);
+        settings::Settings::default()
+    });
+    let fullscreen_type = if settings.fullscreen {
+        FullscreenType::Desktop
+    } else {
+        FullscreenType::Windowed
+    };
+
     let (mut ctx, event_loop) = ContextBuilder::new("bobble_clone", "author_name")
         .window_setup(ggez::conf::WindowSetup::default().title("bobble_clone"))
-        .window_mode(WindowMode::default().dimensions(800.0, 480.0))
+        .window_mode(
+            WindowMode::default()
+                .dimensions(settings.window.0, settings.window.1)
+                .fullscreen_type(fullscreen_type)
+                .resizable(true)
+                .min_dimensions(camera::MIN_SCREEN.0, camera::MIN_SCREEN.1)
+        )
         .add_resource_path("./resources")
         .build()?;
 
-    let game = game::Game::new(&mut ctx);
+    // --dev reloads changed resources while the game runs
+    let dev = args.iter().any(|arg| arg == "--dev");
+    let game = game::Game::new(&mut ctx, settings, dev);
     event::run(ctx, event_loop, game)
 }
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use crate::grid::{ GRID_HEIGHT, GRID_WIDTH };
+
+/// A grid cell `(x, y)`; a standing cell is the empty cell right above a block.
+pub type CellPos = (usize, usize);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EdgeKind {
+    /// To the neighbouring standing cell on the same platform.
+    Walk,
+    /// Off the end of a platform down to wherever the fall ends. Falling out
+    /// of the bottom of the map comes back in at the top, like in the game.
+    Drop,
+    /// Straight up through the platforms above (they only block from above).
+    Jump,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Edge {
+    pub to: CellPos,
+    pub kind: EdgeKind,
+    /// Cells climbed by a jump, 0 for walks and drops.
+    pub rise: usize,
+}
+
+/// Where enemies can stand and how they get from one standing cell to another.
+/// Built from the collision grid only, so it works without a window.
+pub struct NavGraph {
+    standable: [[bool; GRID_WIDTH]; GRID_HEIGHT],
+    edges: Vec<Vec<Edge>>, // per cell, indexed by y * GRID_WIDTH + x
+}
+
+fn index(cell: CellPos) -> usize {
+    cell.1 * GRID_WIDTH + cell.0
+}
+
+impl NavGraph {
+    pub fn build(grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT]) -> Self {
+        let mut standable = [[false; GRID_WIDTH]; GRID_HEIGHT];
+        for y in 0..GRID_HEIGHT - 1 {
+            for x in 0..GRID_WIDTH {
+                standable[y][x] = !grid[y][x] && grid[y + 1][x];
+            }
+        }
+
+        let mut edges = vec![vec![]; GRID_WIDTH * GRID_HEIGHT];
+        for y in 0..GRID_HEIGHT {
+            for x in 0..GRID_WIDTH {
+                if !standable[y][x] {
+                    continue;
+                }
+                let cell_edges = &mut edges[index((x, y))];
+
+                for side in [x.wrapping_sub(1), x + 1] {
+                    if side >= GRID_WIDTH || grid[y][side] {
+                        continue;
+                    }
+                    if standable[y][side] {
+                        cell_edges.push(Edge { to: (side, y), kind: EdgeKind::Walk, rise: 0 });
+                        continue;
+                    }
+                    // Fall down the column next to the platform end
+                    let landing = (1..GRID_HEIGHT)
+                        .map(|fallen| (y + fallen) % GRID_HEIGHT)
+                        .find(|&row| standable[row][side]);
+                    if let Some(row) = landing {
+                        cell_edges.push(Edge { to: (side, row), kind: EdgeKind::Drop, rise: 0 });
+                    }
+                }
+
+                // Every platform straight above, the pathfinding picks the
+                // highest one the jumper can reach
+                for rise in 2..=y {
+                    if standable[y - rise][x] {
+                        cell_edges.push(Edge { to: (x, y - rise), kind: EdgeKind::Jump, rise });
+                    }
+                }
+            }
+        }
+
+        NavGraph { standable, edges }
+    }
+
+    pub fn is_standable(&self, cell: CellPos) -> bool {
+        cell.0 < GRID_WIDTH && cell.1 < GRID_HEIGHT && self.standable[cell.1][cell.0]
+    }
+
+    /// All standing cells, top left first.
+    pub fn standing_cells(&self) -> Vec<CellPos> {
+        (0..GRID_HEIGHT)
+            .flat_map(|y| (0..GRID_WIDTH).map(move |x| (x, y)))
+            .filter(|&cell| self.is_standable(cell))
+            .collect()
+    }
+
+    /// The standing cell something at `cell` ends up on when it falls straight
+    /// down, or `None` for a column without any floor.
+    pub fn landing_below(&self, cell: CellPos) -> Option<CellPos> {
+        let x = cell.0.min(GRID_WIDTH - 1);
+        (0..GRID_HEIGHT)
+            .map(|fallen| (cell.1.min(GRID_HEIGHT - 1) + fallen) % GRID_HEIGHT)
+            .map(|row| (x, row))
+            .find(|&below| self.is_standable(below))
+    }
+
+    /// The edges usable by an enemy that can jump `reach` cells high. Jumps go
+    /// as high as they can, so of the platforms above only the highest one in
+    /// reach can be landed on.
+    pub fn edges(&self, cell: CellPos, reach: usize) -> impl Iterator<Item = &Edge> {
+        let edges = &self.edges[index(cell)];
+        let jump = edges
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Jump && edge.rise <= reach)
+            .max_by_key(|edge| edge.rise);
+        edges
+            .iter()
+            .filter(|edge| edge.kind != EdgeKind::Jump)
+            .chain(jump)
+    }
+
+    /// First edge of a shortest path from `from` to `to`, counting edges.
+    /// `None` when already there or when `to` can't be reached.
+    pub fn next_step(&self, from: CellPos, to: CellPos, reach: usize) -> Option<Edge> {
+        if from == to || !self.is_standable(from) || !self.is_standable(to) {
+            return None;
+        }
+        // Breadth-first search, remembering the first edge taken on the way
+        // to every cell
+        let mut first_edge: Vec<Option<Edge>> = vec![None; GRID_WIDTH * GRID_HEIGHT];
+        let mut queue = VecDeque::new();
+        for edge in self.edges(from, reach) {
+            if first_edge[index(edge.to)].is_none() && edge.to != from {
+                first_edge[index(edge.to)] = Some(*edge);
+                queue.push_back(edge.to);
+            }
+        }
+        while let Some(cell) = queue.pop_front() {
+            let first = first_edge[index(cell)];
+            if cell == to {
+                return first;
+            }
+            for edge in self.edges(cell, reach) {
+                if first_edge[index(edge.to)].is_none() && edge.to != from {
+                    first_edge[index(edge.to)] = first;
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A floor along the bottom row, plus blocks from `x` to `x_end` on each
+    /// given row `y`.
+    fn grid(platforms: &[(usize, usize, usize)]) -> [[bool; GRID_WIDTH]; GRID_HEIGHT] {
+        let mut grid = [[false; GRID_WIDTH]; GRID_HEIGHT];
+        grid[GRID_HEIGHT - 1] = [true; GRID_WIDTH];
+        for &(y, x, x_end) in platforms {
+            grid[y][x..=x_end].fill(true);
+        }
+        grid
+    }
+
+    const FLOOR: usize = GRID_HEIGHT - 2;
+
+    fn edge(to: CellPos, kind: EdgeKind, rise: usize) -> Edge {
+        Edge { to, kind, rise }
+    }
+
+    #[test]
+    fn build_links_walks_drops_and_jumps() {
+        let nav = NavGraph::build(&grid(&[(12, 5, 10)]));
+        assert!(nav.is_standable((5, 11)) && nav.is_standable((7, FLOOR)));
+        assert!(!nav.is_standable((4, 11)) && !nav.is_standable((7, 12)));
+
+        let from_platform_end: Vec<Edge> = nav.edges((5, 11), 0).copied().collect();
+        assert_eq!(from_platform_end, [
+            edge((4, FLOOR), EdgeKind::Drop, 0),
+            edge((6, 11), EdgeKind::Walk, 0),
+        ]);
+
+        let from_below: Vec<Edge> = nav.edges((7, FLOOR), 10).copied().collect();
+        assert!(from_below.contains(&edge((7, 11), EdgeKind::Jump, FLOOR - 11)));
+        assert!(from_below.contains(&edge((6, FLOOR), EdgeKind::Walk, 0)));
+        assert!(from_below.contains(&edge((8, FLOOR), EdgeKind::Walk, 0)));
+    }
+
+    #[test]
+    fn edges_keep_only_the_highest_jump_in_reach() {
+        let nav = NavGraph::build(&grid(&[(12, 5, 10), (8, 5, 10)]));
+        let jumps = |reach| -> Vec<Edge> {
+            nav.edges((7, FLOOR), reach)
+                .filter(|edge| edge.kind == EdgeKind::Jump)
+                .copied()
+                .collect()
+        };
+        assert_eq!(jumps(3), []);
+        assert_eq!(jumps(6), [edge((7, 11), EdgeKind::Jump, 5)]);
+        assert_eq!(jumps(10), [edge((7, 7), EdgeKind::Jump, 9)]);
+    }
+
+    #[test]
+    fn next_step_takes_the_shortest_path() {
+        let nav = NavGraph::build(&grid(&[(12, 5, 10)]));
+        assert_eq!(
+            nav.next_step((7, FLOOR), (7, 11), 6),
+            Some(edge((7, 11), EdgeKind::Jump, 5))
+        );
+        // Two walks and a jump beat going around
+        assert_eq!(
+            nav.next_step((12, FLOOR), (10, 11), 6),
+            Some(edge((11, FLOOR), EdgeKind::Walk, 0))
+        );
+        // Off the platform's end rather than back along it
+        assert_eq!(
+            nav.next_step((6, 11), (2, FLOOR), 6),
+            Some(edge((5, 11), EdgeKind::Walk, 0))
+        );
+    }
+
+    #[test]
+    fn next_step_is_none_without_a_way() {
+        let nav = NavGraph::build(&grid(&[(12, 5, 10)]));
+        assert_eq!(nav.next_step((7, FLOOR), (7, 11), 3), None);
+        assert_eq!(nav.next_step((7, FLOOR), (7, FLOOR), 6), None);
+        assert_eq!(nav.next_step((7, FLOOR), (7, 5), 6), None); // not a standing cell
+    }
+}
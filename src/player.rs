@@ -1,8 +1,23 @@
-use crate::grid::GRID_WIDTH;
-use ggez::graphics::DrawParam;
-use crate::utils::{ check_collision_player, get_y_pos_correction };
+use ggez::graphics::Color;
+
+use crate::animation::{ Animator, Clips };
+use crate::camera::Camera;
+use crate::grid::{ GRID_HEIGHT, GRID_WIDTH };
+use crate::utils::{ check_collision_player, COLLISION_MARGIN, GRAVITY };
 use crate::game::Game;
 
+/// Length of the death animation in seconds.
+const DEATH_TIME: f32 = 1.5;
+/// Seconds the player can't be hit after respawning.
+const INVULNERABLE_TIME: f32 = 2.5;
+/// Spare lives drawn as icons in the HUD, any more are shown as a plus.
+const HUD_MAX_LIVES: u32 = 5;
+/// Every time a player's score passes another multiple of this they get a life.
+const EXTRA_LIFE_SCORE: i32 = 5000;
+/// Bub and Bob.
+pub const PLAYER_NAMES: [&str; 2] = ["Bub", "Bob"];
+const PLAYER_TINTS: [(f32, f32, f32); 2] = [(1.0, 1.0, 1.0), (0.55, 0.75, 1.0)];
+
 #[derive(PartialEq)]
 pub enum PlayerState {
     Idle,
@@ -14,113 +29,211 @@ pub struct Player {
     pub pos: (f32, f32),
     pub velocity: (f32, f32),
     pub view_right: bool,
-    current_frame: usize,
-    frame_timer: f32,
+    pub lives: u32, // including the one currently played
+    pub score: i32,
+    pub next_extra_life: i32,
+    pub invulnerable_timer: f32,
+    pub death_timer: f32,
+    pub animator: Animator,
     player_state: PlayerState,
 }
 
 impl Player {
-    pub fn new(x: f32, y: f32) -> Self {
+    pub fn new(x: f32, y: f32, lives: u32, clips: &Clips) -> Self {
         Player {
             pos: (x, y),
             velocity: (0.0, 0.0),
             view_right: true,
-            current_frame: 0,
-            frame_timer: 0.0,
+            lives,
+            score: 0,
+            next_extra_life: EXTRA_LIFE_SCORE,
+            invulnerable_timer: 0.0,
+            death_timer: 0.0,
+            animator: Animator::new(clips.get("player.idle")),
             player_state: PlayerState::Idle,
         }
     }
 
-    pub fn update(game: &mut Game, ctx: &mut ggez::Context) {
-        game.player.velocity.1 += game.block_size / 50.0; // Gravitation
+    /// Puts the player back at `pos`, briefly invulnerable, keeping the lives.
+    pub fn respawn(&mut self, pos: (f32, f32)) {
+        self.pos = pos;
+        self.velocity = (0.0, 0.0);
+        self.view_right = true;
+        self.invulnerable_timer = INVULNERABLE_TIME;
+        self.death_timer = 0.0;
+        self.player_state = PlayerState::Idle;
+    }
+
+    pub fn is_dying(&self) -> bool {
+        self.death_timer > 0.0
+    }
+
+    /// Whether enemies and their bullets can hurt the player right now.
+    pub fn is_vulnerable(&self) -> bool {
+        self.is_active() && self.invulnerable_timer <= 0.0
+    }
+
+    /// In the game and not dying, so able to move, pop bubbles and collect fruit.
+    pub fn is_active(&self) -> bool {
+        !self.is_dying() && !self.is_out()
+    }
+
+    /// Costs a life and starts the death animation, unless the player can't be
+    /// hit right now. Tells whether the hit counted.
+    pub fn hit(&mut self) -> bool {
+        if !self.is_vulnerable() {
+            return false;
+        }
+        self.lives = self.lives.saturating_sub(1);
+        self.death_timer = DEATH_TIME;
+        self.velocity = (0.0, 0.0);
+        true
+    }
+
+    /// Out of lives and done dying; out players sit out the rest of the run.
+    pub fn is_out(&self) -> bool {
+        self.lives == 0 && !self.is_dying()
+    }
+
+    /// Adds points, with an extra life every time the score passes another
+    /// multiple of `EXTRA_LIFE_SCORE`.
+    pub fn add_score(&mut self, points: i32) {
+        self.score += points;
+        while self.score >= self.next_extra_life {
+            self.lives += 1;
+            self.next_extra_life += EXTRA_LIFE_SCORE;
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT],
+        clips: &Clips,
+        spawn: (f32, f32),
+        dt: f32
+    ) {
+        if self.is_out() {
+            return;
+        }
+        if self.is_dying() {
+            self.death_timer -= dt;
+            self.animator.play(&clips.get("player.recoil"));
+            self.animator.update(dt);
+            if !self.is_dying() && self.lives > 0 {
+                self.respawn(spawn);
+            }
+            return;
+        }
+        self.invulnerable_timer = (self.invulnerable_timer - dt).max(0.0);
+
+        self.velocity.1 += GRAVITY * dt; // Gravitation
 
         // Horizontale Bewegung prüfen
-        let next_x = game.player.pos.0 + game.player.velocity.0;
-        let next_y = game.player.pos.1 + game.player.velocity.1;
+        let next_x = self.pos.0 + self.velocity.0 * dt;
+        let next_y = self.pos.1 + self.velocity.1 * dt;
         if
             next_x <= 0.0 ||
-            next_x >= game.window_width ||
-            check_collision_player(
-                &game.grid,
-                next_x,
-                game.player.pos.1 + game.block_size * (game.block_size / 114.285),
-                game.block_size
-            )
+            next_x >= (GRID_WIDTH as f32) ||
+            check_collision_player(grid, next_x, self.pos.1 + COLLISION_MARGIN)
         {
-            // game.player.velocity.0 = 0.0;
+            // self.velocity.0 = 0.0;
+        } else if next_y > (GRID_HEIGHT as f32) - 1.0 {
+            self.pos.0 -= self.velocity.0 * dt * 0.2;
         } else {
-            if next_y > game.window_height - game.block_size {
-                game.player.pos.0 = game.player.pos.0 - game.player.velocity.0 * 0.2;
-            } else {
-                game.player.pos.0 = next_x;
-            }
+            self.pos.0 = next_x;
         }
 
         // Vertikale Bewegung prüfen
         if
-            (check_collision_player(
-                &game.grid,
-                game.player.pos.0,
-                next_y + game.block_size * (game.block_size / 114.285),
-                game.block_size
-            ) && game.player.velocity.1 >= 0.0) ||
-            (next_y < game.block_size && game.player.velocity.1 < 0.0)
+            (check_collision_player(grid, self.pos.0, next_y + COLLISION_MARGIN) &&
+                self.velocity.1 >= 0.0) ||
+            (next_y < 1.0 && self.velocity.1 < 0.0)
         {
-            game.player.velocity.1 = 0.0; // Gravitation stoppen
+            self.velocity.1 = 0.0; // Gravitation stoppen
         } else {
-            game.player.pos.1 = next_y;
+            self.pos.1 = next_y;
         }
-        if next_y > game.window_height {
-            game.player.pos.1 = 0.0;
+        if next_y > (GRID_HEIGHT as f32) {
+            self.pos.1 = 0.0;
         }
 
-        game.player.player_state = match (game.player.velocity.1 < 0.0, game.player.velocity.0) {
+        self.player_state = match (self.velocity.1 < 0.0, self.velocity.0) {
             (true, _) => PlayerState::Jumping,
             (false, v) if v > 0.0 => PlayerState::WalkingRight,
             (false, v) if v < 0.0 => PlayerState::WalkingLeft,
             _ => PlayerState::Idle,
         };
 
-        let delta_time = ctx.time.delta().as_secs_f32();
-        game.player.frame_timer += delta_time;
-        if
-            game.player.frame_timer >= 0.1 &&
-            (game.player.player_state == PlayerState::WalkingRight ||
-                game.player.player_state == PlayerState::WalkingLeft)
-        {
-            game.player.current_frame = (game.player.current_frame + 1) % 4;
-            game.player.frame_timer = 0.0;
-        }
+        let clip = match self.player_state {
+            PlayerState::Idle => "player.idle",
+            PlayerState::WalkingLeft => "player.run_left",
+            PlayerState::WalkingRight => "player.run_right",
+            PlayerState::Jumping if self.view_right => "player.jump_right",
+            PlayerState::Jumping => "player.jump_left",
+        };
+        self.animator.play(&clips.get(clip));
+        self.animator.update(dt);
     }
 
     pub fn draw(canvas: &mut ggez::graphics::Canvas, game: &mut Game) {
-        let player_image = match game.player.player_state {
-            PlayerState::Idle => game.resources.player_images[0].clone(),
-            PlayerState::WalkingLeft =>
-                game.resources.player_images[1 + game.player.current_frame].clone(),
-            PlayerState::WalkingRight =>
-                game.resources.player_images[5 + game.player.current_frame].clone(),
-            PlayerState::Jumping => {
-                if game.player.view_right {
-                    game.resources.player_images[10].clone()
-                } else {
-                    game.resources.player_images[9].clone()
-                }
+        for (index, player) in game.world.players.iter().enumerate() {
+            // Blink while invulnerable
+            if player.is_out() || ((player.invulnerable_timer / 0.1) as i32) % 2 == 1 {
+                continue;
             }
-        };
+            let player_image = game.resources.frame(player.animator.frame());
+            let pos = (player.pos.0 - 1.4, player.pos.1 - 2.5);
+            canvas.draw(player_image, game.camera.sprite(pos, 1.0).color(tint(index)));
+        }
+    }
+
+    /// Health icon for the life being played, one icon per spare life, in the
+    /// bottom left corner of the screen for the first player and the bottom
+    /// right one for the second.
+    pub fn draw_hud(canvas: &mut ggez::graphics::Canvas, game: &mut Game) {
+        let resources = &game.resources;
+        let camera = &game.camera;
+        for (index, player) in game.world.players.iter().enumerate() {
+            let spare_lives = player.lives.saturating_sub(1);
 
-        canvas.draw(
-            &player_image,
-            DrawParam::default()
-                .dest(ggez::mint::Point2 {
-                    x: game.player.pos.0 - game.block_size * 1.4,
-                    y: game.player.pos.1 -
-                    get_y_pos_correction(game.window_width, game.block_size, &player_image),
-                })
-                .scale(ggez::mint::Vector2 {
-                    x: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285,
-                    y: game.block_size / (GRID_WIDTH as f32) + game.block_size / 114.285,
-                })
-        );
+            let mut icons = vec![];
+            if player.lives > 0 {
+                icons.push(resources.image("hud.health"));
+            }
+            for _ in 0..spare_lives.min(HUD_MAX_LIVES) {
+                icons.push(resources.image("hud.life"));
+            }
+            if spare_lives > HUD_MAX_LIVES {
+                icons.push(resources.image("hud.plus"));
+            }
+
+            let mut x = camera.scale * 0.5;
+            for image in icons {
+                let size = Camera::tiles(image);
+                let y = camera.screen.1 - (size.1 + 0.25) * camera.scale;
+                let left = if index == 0 { x } else { camera.screen.0 - x - size.0 * camera.scale };
+                canvas.draw(image, camera.overlay((left, y), 1.0).color(tint(index)));
+                x += (size.0 + 0.25) * camera.scale;
+            }
+        }
     }
 }
+
+/// Sprite color of a player; the second one is a blue-tinted copy of the first.
+pub fn tint(index: usize) -> Color {
+    let (r, g, b) = PLAYER_TINTS[index];
+    Color::new(r, g, b, 1.0)
+}
+
+/// Where enemies go and aim: the closest player still in the game.
+pub fn target(players: &[Player], from: (f32, f32)) -> (f32, f32) {
+    let distance = |player: &&Player| {
+        (player.pos.0 - from.0).powi(2) + (player.pos.1 - from.1).powi(2)
+    };
+    players
+        .iter()
+        .filter(|player| !player.is_out())
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .unwrap_or(&players[0])
+        .pos
+}
@@ -0,0 +1,387 @@
+use std::fmt;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use crate::data::GameData;
+use crate::level::{ Level, LevelError };
+use crate::world::{ Button, Difficulty, Input, World, FIXED_DT };
+
+/// Bumped whenever the file layout or the simulation changes in a way that
+/// makes old recordings play out differently.
+pub const REPLAY_VERSION: u32 = 9;
+const MAGIC: &str = "bobble-replay";
+pub const REPLAY_DIR: &str = "replays";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReplayEvent {
+    pub tick: u64,
+    pub player: usize, // 0 for the first one
+    pub button: Button,
+    pub pressed: bool,
+}
+
+/// A recorded run: everything needed to rebuild the world and feed it the
+/// same inputs on the same ticks, plus the result to check against.
+pub struct Replay {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub players: usize,
+    pub level: usize, // the run started on
+    pub final_tick: u64,
+    pub final_score: i32,
+    pub restarts: Vec<u64>, // ticks at which the level was started over
+    pub events: Vec<ReplayEvent>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Version(u32),
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::Version(version) =>
+                write!(f, "version {} is not supported (expected {})", version, REPLAY_VERSION),
+            ReplayError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn from_run(world: &World, restarts: Vec<u64>, events: Vec<ReplayEvent>) -> Self {
+        Replay {
+            seed: world.seed,
+            difficulty: world.difficulty,
+            players: world.players.len(),
+            level: world.first_level,
+            final_tick: world.tick,
+            final_score: world.score(),
+            restarts,
+            events,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", MAGIC, REPLAY_VERSION);
+        text += &format!("seed {}\n", self.seed);
+        text += &format!("difficulty {}\n", self.difficulty.name());
+        text += &format!("players {}\n", self.players);
+        text += &format!("level {}\n", self.level);
+        text += &format!("ticks {}\n", self.final_tick);
+        text += &format!("score {}\n", self.final_score);
+        for tick in &self.restarts {
+            text += &format!("restart {}\n", tick);
+        }
+        text += "events\n";
+        for event in &self.events {
+            let action = if event.pressed { "down" } else { "up" };
+            text += &format!(
+                "{} {} {} {}\n",
+                event.tick,
+                event.player + 1,
+                action,
+                event.button.name()
+            );
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        let error = |line: usize, message: &str| ReplayError::Parse {
+            line,
+            message: message.to_string(),
+        };
+
+        let (_, header) = lines.next().ok_or_else(|| error(1, "empty file"))?;
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [MAGIC, version] => version.parse::<u32>().map_err(|_| error(1, "bad version"))?,
+            _ => {
+                return Err(error(1, "not a replay file"));
+            }
+        };
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::Version(version));
+        }
+
+        let mut replay = Replay {
+            seed: 0,
+            difficulty: Difficulty::Normal,
+            players: 1,
+            level: 1,
+            final_tick: 0,
+            final_score: 0,
+            restarts: vec![],
+            events: vec![],
+        };
+        let mut in_events = false;
+        for (number, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if in_events {
+                let event = match fields[..] {
+                    [tick, player, action, button] =>
+                        ReplayEvent {
+                            tick: tick.parse().map_err(|_| error(number, "bad tick"))?,
+                            player: match player.parse::<usize>() {
+                                Ok(player) if (1..=replay.players).contains(&player) => player - 1,
+                                _ => {
+                                    return Err(error(number, "bad player"));
+                                }
+                            },
+                            pressed: match action {
+                                "down" => true,
+                                "up" => false,
+                                _ => {
+                                    return Err(error(number, "expected 'down' or 'up'"));
+                                }
+                            },
+                            button: Button::from_name(button).ok_or_else(||
+                                error(number, "unknown button")
+                            )?,
+                        },
+                    _ => {
+                        return Err(
+                            error(number, "expected '<tick> <player> <down|up> <button>'")
+                        );
+                    }
+                };
+                if replay.events.last().is_some_and(|last| last.tick > event.tick) {
+                    return Err(error(number, "events are not in tick order"));
+                }
+                replay.events.push(event);
+                continue;
+            }
+
+            let bad_value = || error(number, &format!("bad value for '{}'", fields[0]));
+            match fields[..] {
+                ["seed", seed] => {
+                    replay.seed = seed.parse().map_err(|_| bad_value())?;
+                }
+                ["difficulty", difficulty] => {
+                    replay.difficulty = Difficulty::from_name(difficulty).ok_or_else(bad_value)?;
+                }
+                ["players", players] => {
+                    replay.players = match players.parse() {
+                        Ok(players @ 1..=2) => players,
+                        _ => {
+                            return Err(bad_value());
+                        }
+                    };
+                }
+                ["level", level] => {
+                    replay.level = level.parse().map_err(|_| bad_value())?;
+                }
+                ["ticks", ticks] => {
+                    replay.final_tick = ticks.parse().map_err(|_| bad_value())?;
+                }
+                ["score", score] => {
+                    replay.final_score = score.parse().map_err(|_| bad_value())?;
+                }
+                ["restart", tick] => {
+                    let tick = tick.parse().map_err(|_| bad_value())?;
+                    if replay.restarts.last().is_some_and(|&last| last > tick) {
+                        return Err(error(number, "restarts are not in tick order"));
+                    }
+                    replay.restarts.push(tick);
+                }
+                ["events"] => {
+                    in_events = true;
+                }
+                _ => {
+                    return Err(error(number, &format!("unknown entry '{}'", line)));
+                }
+            }
+        }
+        Ok(replay)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Replay::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the replay into `REPLAY_DIR` under a timestamped name.
+    pub fn save(&self) -> Result<PathBuf, ReplayError> {
+        fs::create_dir_all(REPLAY_DIR)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let path = Path::new(REPLAY_DIR).join(format!("replay-{}.txt", timestamp));
+        fs::write(&path, self.to_text())?;
+        Ok(path)
+    }
+
+    /// Rebuilds the world from the recorded seed and runs it to the recorded
+    /// tick, feeding the events through the same `Input` path the game uses.
+    pub fn play(&self, data: GameData) -> Result<World, LevelError> {
+        let level = Level::load(self.level)?;
+        let mut world = World::new(self.seed, self.difficulty, self.players, &level, data);
+        let mut inputs = vec![Input::default(); self.players];
+        let mut events = self.events.iter().peekable();
+        let mut restarts = self.restarts.iter().peekable();
+
+        while world.tick < self.final_tick && !world.game_over {
+            // Same as restarting from the pause menu: the level as it was, no keys held
+            while restarts.next_if(|&&tick| tick <= world.tick).is_some() {
+                world.start_level(&Level::load(world.level)?);
+                inputs.fill(Input::default());
+            }
+            while let Some(event) = events.next_if(|event| event.tick <= world.tick) {
+                inputs[event.player].apply(event.button, event.pressed);
+            }
+            world.step(FIXED_DT, &inputs);
+            inputs.iter_mut().for_each(Input::clear_presses);
+
+            // Same progression as the game: the next level starts with no keys held
+            if !world.game_over && world.is_cleared() {
+                match Level::load_next(world.level)? {
+                    Some(level) => {
+                        world.start_level(&level);
+                        inputs.fill(Input::default());
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(world)
+    }
+}
+
+/// Entry point for `--replay <file>`: plays the file back without a window and
+/// returns the process exit code.
+pub fn run_headless(path: &str) -> i32 {
+    let replay = match Replay::load(Path::new(path)) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("could not load replay {}: {}", path, err);
+            return 2;
+        }
+    };
+
+    let data = match GameData::load() {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("could not load game data for replay: {}", err);
+            return 2;
+        }
+    };
+    let world = match replay.play(data) {
+        Ok(world) => world,
+        Err(err) => {
+            eprintln!("could not load level {} for replay: {}", replay.level, err);
+            return 2;
+        }
+    };
+    let score = world.score();
+    if world.tick == replay.final_tick && score == replay.final_score {
+        println!("replay ok: score {} after {} ticks", score, world.tick);
+        0
+    } else {
+        eprintln!(
+            "replay mismatch: expected score {} after {} ticks, got score {} after {} ticks",
+            replay.final_score,
+            replay.final_tick,
+            score,
+            world.tick
+        );
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(tick: u64, button: Button, pressed: bool) -> ReplayEvent {
+        ReplayEvent { tick, player: 0, button, pressed }
+    }
+
+    #[test]
+    fn text_parses_back_unchanged() {
+        let replay = Replay {
+            seed: 42,
+            difficulty: Difficulty::Hard,
+            players: 2,
+            level: 3,
+            final_tick: 900,
+            final_score: 1250,
+            restarts: vec![300, 600],
+            events: vec![
+                event(10, Button::Left, true),
+                ReplayEvent { tick: 10, player: 1, button: Button::Jump, pressed: true },
+                event(25, Button::Left, false)
+            ],
+        };
+        let parsed = Replay::parse(&replay.to_text()).unwrap();
+        assert_eq!(parsed.to_text(), replay.to_text());
+        assert_eq!(parsed.events, replay.events);
+        assert_eq!(parsed.restarts, replay.restarts);
+        assert_eq!((parsed.seed, parsed.players, parsed.level), (42, 2, 3));
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let text = format!("{} {}\nseed 1\nevents\n", MAGIC, REPLAY_VERSION - 1);
+        assert!(matches!(Replay::parse(&text), Err(ReplayError::Version(_))));
+    }
+
+    #[test]
+    fn events_out_of_tick_order_are_rejected() {
+        let text = format!("{} {}\nevents\n20 1 down left\n10 1 up left\n", MAGIC, REPLAY_VERSION);
+        assert!(matches!(Replay::parse(&text), Err(ReplayError::Parse { line: 4, .. })));
+
+        let text = format!("{} {}\nrestart 20\nrestart 10\n", MAGIC, REPLAY_VERSION);
+        assert!(matches!(Replay::parse(&text), Err(ReplayError::Parse { line: 3, .. })));
+    }
+
+    #[test]
+    fn a_recorded_run_plays_back_to_the_same_result() {
+        // Walks right and fires every 20 ticks on the first level, recording
+        // the changes the way the game does
+        let data = GameData::load().unwrap();
+        let mut world = World::new(3, Difficulty::Normal, 1, &Level::load(1).unwrap(), data);
+        let mut events = vec![];
+        let mut last = Input::default();
+        while world.tick < 900 && !world.game_over && !world.is_cleared() {
+            let input = Input {
+                right: (world.tick / 120).is_multiple_of(2),
+                shoot: world.tick % 20 == 10,
+                ..Input::default()
+            };
+            if input.right != last.right {
+                events.push(event(world.tick, Button::Right, input.right));
+            }
+            if input.shoot {
+                events.push(event(world.tick, Button::Shoot, true));
+            }
+            world.step(FIXED_DT, &[input]);
+            last = input;
+        }
+        assert!(world.score() > 0, "the run should have scored");
+        let replay = Replay::from_run(&world, vec![], events);
+        let replay = Replay::parse(&replay.to_text()).unwrap();
+
+        let played = replay.play(GameData::load().unwrap()).unwrap();
+        assert_eq!(played.tick, world.tick);
+        assert_eq!(played.score(), world.score());
+    }
+}
@@ -1,60 +1,213 @@
-use ggez::graphics;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
 
+use ggez::graphics::{ self, ImageFormat };
+
+use crate::assets::{ Asset, AssetManifest };
+use crate::audio::SoundData;
+
+/// Side length of the checkered image standing in for a broken one.
+const PLACEHOLDER_SIZE: u32 = 16;
+/// Manifest groups whose files are sounds; every other asset is images.
+const SOUND_GROUPS: [&str; 2] = ["music", "sfx"];
+
+#[derive(Debug)]
+pub enum Fault {
+    Missing,
+    Corrupt(String),
+}
+
+/// A file of the manifest that could not be loaded.
+#[derive(Debug)]
+pub struct AssetProblem {
+    pub asset: String,
+    pub file: String,
+    pub fault: Fault,
+    /// Marked `critical=yes` in the manifest; the game can't run without it.
+    pub critical: bool,
+}
+
+impl fmt::Display for AssetProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "resources/{} ({}", self.file, self.asset)?;
+        if self.critical {
+            write!(f, ", critical")?;
+        }
+        match &self.fault {
+            Fault::Missing => write!(f, "): missing"),
+            Fault::Corrupt(message) => write!(f, "): corrupt: {}", message),
+        }
+    }
+}
+
+/// Every asset that failed to load. Broken images have already been replaced
+/// by placeholders and broken sounds by silence, so the game can still go on
+/// with `into_resources` unless a critical asset is among them.
+pub struct ResourceError {
+    pub problems: Vec<AssetProblem>,
+    resources: Box<Resources>,
+}
+
+impl ResourceError {
+    pub fn is_critical(&self) -> bool {
+        self.problems.iter().any(|problem| problem.critical)
+    }
+
+    pub fn into_resources(self) -> Resources {
+        *self.resources
+    }
+}
+
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} asset file(s) could not be loaded:", self.problems.len())?;
+        for problem in &self.problems {
+            write!(f, "\n  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every image and sound in the asset manifest, loaded once at startup.
 pub struct Resources {
-    pub player_images: Vec<graphics::Image>,
-    pub grid_image: graphics::Image,
-    pub bullet_image: graphics::Image,
-    pub bobble_image: Vec<graphics::Image>,
-    pub enemy_images: Vec<graphics::Image>,
-    pub menu_images: Vec<graphics::Image>,
-    pub game_over_image: graphics::Image,
-    pub enemy_bullet_image: Vec<graphics::Image>,
+    pub manifest: AssetManifest,
+    images: HashMap<String, graphics::Image>, // by file name
+    sounds: HashMap<String, SoundData>, // by file name, broken ones left out
+    placeholder: graphics::Image, // drawn for any image file that isn't loaded
 }
 
 impl Resources {
-    pub fn load(ctx: &mut ggez::Context) -> Self {
-        Resources {
-            player_images: vec![
-                graphics::Image::from_path(ctx, "/still.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run00.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run01.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run02.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run03.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run10.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run11.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run12.png").unwrap(),
-                graphics::Image::from_path(ctx, "/run13.png").unwrap(),
-                graphics::Image::from_path(ctx, "/jump0.png").unwrap(),
-                graphics::Image::from_path(ctx, "/jump1.png").unwrap()
-            ],
-            grid_image: graphics::Image::from_path(ctx, "/block0.png").unwrap(),
-            bullet_image: graphics::Image::from_path(ctx, "/orb6.png").unwrap(),
-            bobble_image: vec![
-                graphics::Image::from_path(ctx, "/trap00.png").unwrap(),
-                graphics::Image::from_path(ctx, "/trap01.png").unwrap(),
-                graphics::Image::from_path(ctx, "/trap02.png").unwrap(),
-                graphics::Image::from_path(ctx, "/trap03.png").unwrap(),
-                graphics::Image::from_path(ctx, "/trap04.png").unwrap(),
-                graphics::Image::from_path(ctx, "/trap05.png").unwrap(),
-                graphics::Image::from_path(ctx, "/trap06.png").unwrap(),
-                graphics::Image::from_path(ctx, "/trap07.png").unwrap()
-            ],
-            enemy_images: vec![
-                graphics::Image::from_path(ctx, "/robot000.png").unwrap(),
-                graphics::Image::from_path(ctx, "/robot010.png").unwrap()
-            ],
-            menu_images: vec![
-                graphics::Image::from_path(ctx, "/startgame.png").unwrap(),
-                graphics::Image::from_path(ctx, "/Windowsize.png").unwrap(),
-                graphics::Image::from_path(ctx, "/Exit.png").unwrap()
-            ],
-            game_over_image: graphics::Image::from_path(ctx, "/gameover.png").unwrap(),
-            enemy_bullet_image: vec![
-                graphics::Image::from_path(ctx, "/bolt00.png").unwrap(),
-                graphics::Image::from_path(ctx, "/bolt01.png").unwrap(),
-                graphics::Image::from_path(ctx, "/bolt10.png").unwrap(),
-                graphics::Image::from_path(ctx, "/bolt11.png").unwrap()
-            ],
+    pub fn load(ctx: &ggez::Context, manifest: &AssetManifest) -> Result<Self, ResourceError> {
+        let mut resources = Resources {
+            manifest: manifest.clone(),
+            images: HashMap::new(),
+            sounds: HashMap::new(),
+            placeholder: placeholder_image(ctx),
+        };
+        let mut problems = vec![];
+
+        for asset in manifest.assets() {
+            let critical = asset.meta.get("critical").is_some_and(|value| value == "yes");
+            let sound = is_sound(asset);
+            for file in &asset.files {
+                if resources.images.contains_key(file) || resources.sounds.contains_key(file) {
+                    continue;
+                }
+                let loaded = if sound {
+                    load_sound(ctx, file).map(|sound| {
+                        resources.sounds.insert(file.clone(), sound);
+                    })
+                } else {
+                    load_image(ctx, file).map(|image| {
+                        resources.images.insert(file.clone(), image);
+                    })
+                };
+                if let Err(fault) = loaded {
+                    if !sound {
+                        resources.images.insert(file.clone(), resources.placeholder.clone());
+                    }
+                    problems.push(AssetProblem {
+                        asset: asset.name.clone(),
+                        file: file.clone(),
+                        fault,
+                        critical,
+                    });
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(resources)
+        } else {
+            Err(ResourceError { problems, resources: Box::new(resources) })
         }
     }
+
+    /// First image of an asset, the placeholder if there is none.
+    pub fn image(&self, name: &str) -> &graphics::Image {
+        self.frame(self.manifest.files(name).first().map_or("", String::as_str))
+    }
+
+    /// All images of an asset, in manifest order.
+    pub fn images(&self, name: &str) -> Vec<&graphics::Image> {
+        self.manifest
+            .files(name)
+            .iter()
+            .map(|file| self.frame(file))
+            .collect()
+    }
+
+    /// Image of an animation frame, as named by `Animator::frame`. Files that
+    /// aren't loaded images get the placeholder.
+    pub fn frame(&self, file: &str) -> &graphics::Image {
+        self.images.get(file).unwrap_or(&self.placeholder)
+    }
+
+    /// First sound of an asset, none if it couldn't be loaded.
+    pub fn sound(&self, name: &str) -> Option<&SoundData> {
+        self.manifest.files(name).first().and_then(|file| self.sounds.get(file))
+    }
+}
+
+/// Whether the files of `asset` are sounds rather than images.
+fn is_sound(asset: &Asset) -> bool {
+    SOUND_GROUPS.iter().any(|group| {
+        asset.name.strip_prefix(group).is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+fn read_file(ctx: &ggez::Context, file: &str) -> Result<Vec<u8>, Fault> {
+    let mut bytes = vec![];
+    ctx.fs
+        .open(format!("/{}", file))
+        .map_err(|_| Fault::Missing)?
+        .read_to_end(&mut bytes)
+        .map_err(|err| Fault::Corrupt(err.to_string()))?;
+    Ok(bytes)
+}
+
+fn load_image(ctx: &ggez::Context, file: &str) -> Result<graphics::Image, Fault> {
+    let bytes = read_file(ctx, file)?;
+    graphics::Image::from_bytes(ctx, &bytes).map_err(|err| Fault::Corrupt(err.to_string()))
+}
+
+fn load_sound(ctx: &ggez::Context, file: &str) -> Result<SoundData, Fault> {
+    let sound = SoundData::from_bytes(&read_file(ctx, file)?);
+    if !sound.can_play() {
+        return Err(Fault::Corrupt("not a sound the game can decode".to_string()));
+    }
+    Ok(sound)
+}
+
+/// Magenta and black checkers, so a broken image is obvious but harmless.
+fn placeholder_image(ctx: &ggez::Context) -> graphics::Image {
+    let mut pixels = vec![];
+    for y in 0..PLACEHOLDER_SIZE {
+        for x in 0..PLACEHOLDER_SIZE {
+            let magenta = (x / 4 + y / 4) % 2 == 0;
+            pixels.extend_from_slice(if magenta { &[255, 0, 255, 255] } else { &[0, 0, 0, 255] });
+        }
+    }
+    graphics::Image::from_pixels(
+        ctx,
+        &pixels,
+        ImageFormat::Rgba8UnormSrgb,
+        PLACEHOLDER_SIZE,
+        PLACEHOLDER_SIZE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assets_are_sounds_by_group_not_extension() {
+        let manifest = AssetManifest::parse(
+            "sfx.pop pop.wav\nmusic.theme theme.ogg\nplayer.idle still.PNG\nsfxs.odd odd.jpg\n"
+        ).unwrap();
+        let sounds: Vec<bool> = manifest.assets().iter().map(is_sound).collect();
+        assert_eq!(sounds, [true, true, false, false]);
+    }
 }
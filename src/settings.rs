@@ -0,0 +1,218 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::actions::{ Action, Bindings };
+use crate::keys::{ key_name, parse_key };
+use crate::world::Difficulty;
+
+/// Player options, kept in `settings.txt` in the user's config directory:
+///
+///     window <width> <height>
+///     fullscreen <yes|no>
+///     music_volume <0..1>
+///     sfx_volume <0..1>
+///     mute <yes|no>
+///     difficulty <easy|normal|hard>
+///     key <action> <key>
+///     key2 <action> <key>
+///
+/// `key` binds the first player's keys, which also work the menus, `key2` the
+/// second player's. Actions are named like `move_left` or `confirm`, see
+/// `Action::name`, and keys like ggez names them (`Left`, `Space`, `A`, ...).
+/// Several lines give an action several keys. Missing entries keep their
+/// defaults. Lines starting with `;` are comments.
+pub const SETTINGS_FILE: &str = "settings.txt";
+/// Same directory name ggez uses for the game's user files.
+const CONFIG_DIR_NAME: &str = "bobble_clone";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub window: (f32, f32),
+    pub fullscreen: bool,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool,
+    pub difficulty: Difficulty,
+    pub bindings: [Bindings; 2], // by player
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window: (800.0, 480.0),
+            fullscreen: false,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            muted: false,
+            difficulty: Difficulty::Normal,
+            bindings: [Bindings::default(), Bindings::second_player()],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    NoConfigDir,
+    Io(PathBuf, io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::NoConfigDir => write!(f, "no user config directory"),
+            SettingsError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            SettingsError::Parse { path, line, message } =>
+                write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+/// The game's directory in the platform's config directory, like ggez's own
+/// user config directory. Settings and high scores live there.
+pub fn config_dir() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    config_dir.map(|dir| dir.join(CONFIG_DIR_NAME))
+}
+
+pub fn settings_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(SETTINGS_FILE))
+}
+
+impl Settings {
+    /// Reads the settings file; a missing file means the defaults.
+    pub fn load() -> Result<Self, SettingsError> {
+        let path = settings_path().ok_or(SettingsError::NoConfigDir)?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(Settings::default());
+            }
+            Err(err) => {
+                return Err(SettingsError::Io(path, err));
+            }
+        };
+        Settings::parse(&text).map_err(|(line, message)| SettingsError::Parse {
+            path,
+            line,
+            message,
+        })
+    }
+
+    pub fn save(&self) -> Result<PathBuf, SettingsError> {
+        let path = settings_path().ok_or(SettingsError::NoConfigDir)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| SettingsError::Io(dir.to_path_buf(), err))?;
+        }
+        fs::write(&path, self.to_text()).map_err(|err| SettingsError::Io(path.clone(), err))?;
+        Ok(path)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("; Bobble_Clone settings, rewritten when options change\n");
+        text += &format!("window {} {}\n", self.window.0, self.window.1);
+        text += &format!("fullscreen {}\n", if self.fullscreen { "yes" } else { "no" });
+        text += &format!("music_volume {}\n", self.music_volume);
+        text += &format!("sfx_volume {}\n", self.sfx_volume);
+        text += &format!("mute {}\n", if self.muted { "yes" } else { "no" });
+        text += &format!("difficulty {}\n", self.difficulty.name());
+        for (entry, bindings) in ["key", "key2"].into_iter().zip(&self.bindings) {
+            for (action, key) in bindings.all() {
+                text += &format!("{} {} {}\n", entry, action.name(), key_name(*key));
+            }
+        }
+        text
+    }
+
+    /// Parses the settings file, reporting problems as 1-based `(line, message)`.
+    pub fn parse(text: &str) -> Result<Self, (usize, String)> {
+        let mut settings = Settings::default();
+        let mut rebound = vec![]; // players and actions whose default keys are replaced
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let bad_value = || (number, format!("bad value for '{}'", fields[0]));
+            match fields[..] {
+                ["window", width, height] => {
+                    let width: f32 = width.parse().map_err(|_| bad_value())?;
+                    let height: f32 = height.parse().map_err(|_| bad_value())?;
+                    if width < 1.0 || height < 1.0 {
+                        return Err(bad_value());
+                    }
+                    settings.window = (width, height);
+                }
+                ["fullscreen", fullscreen] => {
+                    settings.fullscreen = match fullscreen {
+                        "yes" => true,
+                        "no" => false,
+                        _ => {
+                            return Err(bad_value());
+                        }
+                    };
+                }
+                ["mute", muted] => {
+                    settings.muted = match muted {
+                        "yes" => true,
+                        "no" => false,
+                        _ => {
+                            return Err(bad_value());
+                        }
+                    };
+                }
+                ["music_volume", volume] => {
+                    settings.music_volume = parse_volume(volume).ok_or_else(bad_value)?;
+                }
+                ["sfx_volume", volume] => {
+                    settings.sfx_volume = parse_volume(volume).ok_or_else(bad_value)?;
+                }
+                ["difficulty", difficulty] => {
+                    settings.difficulty = Difficulty::from_name(difficulty).ok_or_else(bad_value)?;
+                }
+                [entry @ ("key" | "key2"), action, key] => {
+                    let player = if entry == "key" { 0 } else { 1 };
+                    let action = Action::from_name(action).ok_or((
+                        number,
+                        format!("unknown action '{}'", action),
+                    ))?;
+                    let key = parse_key(key).ok_or((number, format!("unknown key '{}'", key)))?;
+                    let bindings = &mut settings.bindings[player];
+                    if rebound.contains(&(player, action)) {
+                        bindings.add(action, key);
+                    } else {
+                        bindings.bind(action, key);
+                        rebound.push((player, action));
+                    }
+                }
+                _ => {
+                    return Err((number, format!("unknown entry '{}'", line)));
+                }
+            }
+        }
+        Ok(settings)
+    }
+}
+
+fn parse_volume(text: &str) -> Option<f32> {
+    text.parse::<f32>().ok().filter(|volume| (0.0..=1.0).contains(volume))
+}
@@ -1,16 +1,24 @@
-use ggez::graphics::Image;
 use rand::Rng;
 
-use crate::{ enemy, enemy_bullet, game::Game, grid::{ GRID_HEIGHT, GRID_WIDTH }, player, utils };
+use crate::{
+    animation::Animator,
+    bubble,
+    enemy_bullet,
+    enemy_type::Projectile,
+    grid::{ GRID_HEIGHT, GRID_WIDTH },
+    player,
+    world::World,
+};
 
-pub fn check_collision(
-    grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT],
-    x: f32,
-    y: f32,
-    block_size: f32
-) -> bool {
-    let grid_x = (x / block_size) as usize;
-    let grid_y = (y / block_size) as usize;
+/// Downward acceleration in tiles per second².
+pub const GRAVITY: f32 = 72.0;
+/// How far below their feet walkers check for blocks, in tiles.
+pub const COLLISION_MARGIN: f32 = 0.21875;
+
+/// Whether the tile at `(x, y)` is a block. Outside the grid is empty.
+pub fn check_collision(grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT], x: f32, y: f32) -> bool {
+    let grid_x = x as usize;
+    let grid_y = y as usize;
 
     if grid_x < GRID_WIDTH && grid_y < GRID_HEIGHT {
         grid[grid_y][grid_x]
@@ -19,71 +27,113 @@ pub fn check_collision(
     }
 }
 
-pub fn check_collision_player(
-    grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT],
-    x: f32,
-    y: f32,
-    block_size: f32
-) -> bool {
-    check_collision(grid, x, y, block_size) ||
-        check_collision(grid, x + block_size, y, block_size) ||
-        check_collision(grid, x - block_size, y, block_size)
+/// Position of something standing in the given grid cell. The feet start just
+/// above the collision margin, otherwise the walls check hits the floor block.
+pub fn cell_to_pos(cell: (usize, usize)) -> (f32, f32) {
+    let margin = COLLISION_MARGIN + 0.04;
+    ((cell.0 as f32) + 0.5, ((cell.1 + 1) as f32) - margin)
 }
 
-pub fn get_y_pos_correction(window_width: f32, block_size: f32, image: &Image) -> f32 {
-    let y_pos_correction;
-    if window_width >= 1920.0 {
-        y_pos_correction =
-            ((image.height() as f32) * block_size) / (GRID_WIDTH as f32) + block_size / 114.285;
-    } else {
-        y_pos_correction = block_size * 2.5;
-    }
-    y_pos_correction
+/// Grid cell containing `pos`, clamped to the grid.
+pub fn pos_to_cell(pos: (f32, f32)) -> (usize, usize) {
+    (
+        (pos.0.max(0.0) as usize).min(GRID_WIDTH - 1),
+        (pos.1.max(0.0) as usize).min(GRID_HEIGHT - 1),
+    )
 }
 
-pub fn update_objects(game: &mut Game, ctx: &mut ggez::Context, delta_time: f32) {
-    //Player
-    player::Player::update(game, ctx);
+pub fn check_collision_player(grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT], x: f32, y: f32) -> bool {
+    check_collision(grid, x, y) ||
+        check_collision(grid, x + 1.0, y) ||
+        check_collision(grid, x - 1.0, y)
+}
 
-    //Enemy
-    game.enemy_spawn_timer -= delta_time;
-    if game.enemy_spawn_timer <= 0.0 {
-        game.enemies.push(
-            enemy::Enemy::new((random_f32() * game.block_size * 10.0, game.block_size), (1.0, 0.0))
-        );
-        game.enemy_spawn_timer = 10.0; // Timer zurücksetzen
+pub fn update_objects(world: &mut World, delta_time: f32) {
+    //Players; the game is over once none is left
+    for (player, spawn) in world.players.iter_mut().zip(world.spawns) {
+        player.update(&world.grid, &world.data.clips, spawn, delta_time);
+    }
+    if world.players.iter().all(|player| player.is_out()) {
+        world.game_over = true;
     }
-    game.enemies.iter_mut().for_each(|enemy| enemy.update(&game.grid, game.block_size));
-    game.enemies.retain(|enemy| !enemy.is_off_screen(game.block_size));
+
+    //Enemy
+    world.enemies
+        .iter_mut()
+        .for_each(|enemy| {
+            let target = player::target(&world.players, enemy.pos);
+            enemy.update(
+                &world.grid,
+                &world.nav,
+                target,
+                delta_time,
+                &mut world.rng
+            );
+            enemy.animate(&world.data.clips, delta_time);
+        });
+    world.enemies.retain(|enemy| !enemy.is_off_screen());
 
     //Bullets
-    game.bullets.iter_mut().for_each(|bullet| bullet.update());
-    game.bullets.retain(|bullet| !bullet.is_off_screen(game.block_size));
+    world.bullets.iter_mut().for_each(|bullet| bullet.update(delta_time));
+    world.bullets.retain(|bullet| !bullet.is_off_screen());
+    // Shots that ran out of steam become empty bubbles
+    let mut spent = vec![];
+    world.bullets.retain(|bullet| {
+        if bullet.is_spent() {
+            spent.push(bubble::Bubble::new(bullet.pos, None, &world.data.clips));
+        }
+        !bullet.is_spent()
+    });
+    world.bubbles.append(&mut spent);
 
     //Bubbles
-    game.bubbles.iter_mut().for_each(|bubble| bubble.update(ctx));
-
-    // //enemy_bubbles
-    for enemy in &game.enemies {
-        if utils::random_f32() < 0.005 {
-            game.enemy_bullets.push(enemy_bullet::EnemyBullet {
-                pos: enemy.pos,
-                velocity: (
-                    if enemy.velocity.0 > 0.0 {
-                        game.block_size / 3.0
-                    } else {
-                        -(game.block_size / 3.0)
-                    },
-                    0.0,
-                ),
-            });
+    world.bubbles
+        .iter_mut()
+        .for_each(|bubble| bubble.update(delta_time, &world.currents));
+    // Bubbles that ran out of time burst, trapped enemies break out angrier than before
+    for bubble in world.bubbles.iter_mut().filter(|bubble| bubble.is_expired()) {
+        if let Some(mut enemy) = bubble.pop(&world.data.clips) {
+            enemy.pos = (bubble.pos.0, bubble.pos.1 + 1.0);
+            enemy.velocity.1 = 0.0;
+            enemy.enrage();
+            world.enemies.push(enemy);
         }
     }
-    game.enemy_bullets.iter_mut().for_each(|bullet| bullet.update());
-    game.enemy_bullets.retain(|bullet| !bullet.is_off_screen());
-}
+    world.bubbles.retain(|bubble| !bubble.is_gone());
+    world.combo_timer -= delta_time;
 
-pub fn random_f32() -> f32 {
-    let mut rng = rand::thread_rng();
-    rng.gen::<f32>()
+    //Items
+    world.items
+        .iter_mut()
+        .for_each(|item| item.update(&world.grid, delta_time));
+    world.items.retain(|item| !item.is_expired());
+
+    // Enemy projectiles: bolts fly straight ahead, sparks towards the closest player
+    for enemy in world.enemies.iter_mut() {
+        let Some(projectile) = enemy.enemy_type.projectile else {
+            continue;
+        };
+        if world.rng.gen::<f32>() >= enemy.enemy_type.fire_rate * delta_time {
+            continue;
+        }
+        let speed = projectile.speed();
+        let direction = match projectile {
+            Projectile::Bolt => (if enemy.velocity.0 > 0.0 { 1.0 } else { -1.0 }, 0.0),
+            Projectile::Spark => {
+                let target = player::target(&world.players, enemy.pos);
+                let target = (target.0, target.1 - 1.0);
+                let offset = (target.0 - enemy.pos.0, target.1 - enemy.pos.1);
+                let length = (offset.0 * offset.0 + offset.1 * offset.1).sqrt().max(0.04);
+                (offset.0 / length, offset.1 / length)
+            }
+        };
+        enemy.fire(&world.data.clips);
+        world.enemy_bullets.push(enemy_bullet::EnemyBullet {
+            pos: enemy.pos,
+            velocity: (direction.0 * speed, direction.1 * speed),
+            animator: Animator::new(world.data.clips.get(projectile.clip_name())),
+        });
+    }
+    world.enemy_bullets.iter_mut().for_each(|bullet| bullet.update(delta_time));
+    world.enemy_bullets.retain(|bullet| !bullet.is_off_screen());
 }
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+
+/// Seconds between two looks at the watched files.
+const POLL_INTERVAL: f32 = 0.5;
+
+/// Notices added, changed and deleted files below a directory, for `--dev`.
+/// Polls modification times, which is plenty for a handful of resource files
+/// and works the same on every platform.
+pub struct Watcher {
+    root: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+    timer: f32,
+}
+
+impl Watcher {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let modified = scan(&root);
+        Watcher { root, modified, timer: POLL_INTERVAL }
+    }
+
+    /// Files that changed since the last poll, none in between two polls.
+    pub fn poll(&mut self, dt: f32) -> Vec<PathBuf> {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return vec![];
+        }
+        self.timer = POLL_INTERVAL;
+
+        let modified = scan(&self.root);
+        let mut changed: Vec<PathBuf> = modified
+            .iter()
+            .filter(|(path, time)| self.modified.get(*path) != Some(*time))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(self.modified.keys().filter(|path| !modified.contains_key(*path)).cloned());
+        changed.sort();
+        self.modified = modified;
+        changed
+    }
+}
+
+fn scan(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    let mut modified = HashMap::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return modified;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            modified.extend(scan(&path));
+        } else if let Ok(time) = metadata.modified() {
+            modified.insert(path, time);
+        }
+    }
+    modified
+}
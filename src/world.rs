@@ -0,0 +1,578 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::data::GameData;
+use crate::grid::{ self, GRID_HEIGHT, GRID_WIDTH };
+use crate::level::{ Current, Level };
+use crate::nav::NavGraph;
+use crate::{ bubble, bullet, enemy, enemy_bullet, item, player, utils };
+
+/// Simulation rate; every step advances the world by exactly `FIXED_DT`.
+pub const TICKS_PER_SECOND: u32 = 60;
+pub const FIXED_DT: f32 = 1.0 / (TICKS_PER_SECOND as f32);
+/// Seconds after a pop in which the next pop counts towards a combo.
+const COMBO_WINDOW: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Button {
+    Left,
+    Right,
+    Jump,
+    Shoot,
+}
+
+impl Button {
+    pub const ALL: [Button; 4] = [Button::Left, Button::Right, Button::Jump, Button::Shoot];
+
+    /// Name in replay and settings files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::Left => "left",
+            Button::Right => "right",
+            Button::Jump => "jump",
+            Button::Shoot => "shoot",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Button::ALL.into_iter().find(|button| button.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    /// Name in replay and settings files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Difficulty::ALL.into_iter().find(|difficulty| difficulty.name() == name)
+    }
+
+    /// Lives each player starts a run with.
+    pub fn start_lives(self) -> u32 {
+        match self {
+            Difficulty::Easy => 5,
+            Difficulty::Normal => 3,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    /// Factor on the walking and flying speed of every enemy.
+    pub fn enemy_speed(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
+        }
+    }
+}
+
+/// Gameplay input for one simulation step. Directions are held states, `jump` and
+/// `shoot` are presses that the caller clears after the step consumed them.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub shoot: bool,
+}
+
+impl Input {
+    pub fn apply(&mut self, button: Button, pressed: bool) {
+        match button {
+            Button::Left => {
+                self.left = pressed;
+            }
+            Button::Right => {
+                self.right = pressed;
+            }
+            // Releases don't matter for presses, they are cleared after every step
+            Button::Jump => {
+                self.jump |= pressed;
+            }
+            Button::Shoot => {
+                self.shoot |= pressed;
+            }
+        }
+    }
+
+    pub fn clear_presses(&mut self) {
+        self.jump = false;
+        self.shoot = false;
+    }
+}
+
+/// Something that happened during a step, for the game to play a sound for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    Shoot,
+    Jump,
+    Capture, // an enemy got trapped in a bubble
+    Pop,
+    Fruit,
+    Death,
+}
+
+/// The whole gameplay state. Knows nothing about ggez, so it can be stepped
+/// without a window, e.g. from tests. All randomness comes from `rng`, so the
+/// same seed and the same inputs always lead to the same state.
+pub struct World {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub first_level: usize, // the run started on it, replays start there too
+    pub level: usize,
+    pub rng: StdRng,
+    pub tick: u64,
+    pub spawns: [(f32, f32); 2],
+    pub players: Vec<player::Player>, // one, or two playing together
+
+    pub data: GameData,
+    pub enemies: Vec<enemy::Enemy>,
+    pub bullets: Vec<bullet::Bullet>,
+    pub bubbles: Vec<bubble::Bubble>,
+    pub enemy_bullets: Vec<enemy_bullet::EnemyBullet>,
+    pub items: Vec<item::Item>,
+    pub combo: usize,
+    pub combo_timer: f32,
+    pub grid: [[bool; GRID_WIDTH]; GRID_HEIGHT],
+    pub nav: NavGraph,
+    pub currents: [[Current; GRID_WIDTH]; GRID_HEIGHT],
+    pub game_over: bool,
+    pub events: Vec<Event>, // of the last step
+}
+
+impl World {
+    pub fn new(
+        seed: u64,
+        difficulty: Difficulty,
+        players: usize,
+        level: &Level,
+        data: GameData
+    ) -> Self {
+        let mut world = World {
+            seed,
+            difficulty,
+            first_level: level.id,
+            level: level.id,
+            rng: StdRng::seed_from_u64(seed),
+            tick: 0,
+            spawns: [(0.0, 0.0); 2],
+            players: (0..players)
+                .map(|_| player::Player::new(0.0, 0.0, difficulty.start_lives(), &data.clips))
+                .collect(),
+            data,
+            enemies: vec![],
+            bullets: vec![],
+            bubbles: vec![],
+            enemy_bullets: vec![],
+            items: vec![],
+            combo: 0,
+            combo_timer: 0.0,
+            grid: [[false; GRID_WIDTH]; GRID_HEIGHT],
+            nav: NavGraph::build(&[[false; GRID_WIDTH]; GRID_HEIGHT]),
+            currents: [[Current::Up; GRID_WIDTH]; GRID_HEIGHT],
+            game_over: false,
+            events: vec![],
+        };
+        world.start_level(level);
+        world
+    }
+
+    /// Replaces the layout and everything living in it, keeping score, tick and RNG.
+    pub fn start_level(&mut self, level: &Level) {
+        self.spawns = level.player_spawns.map(utils::cell_to_pos);
+        self.level = level.id;
+        self.grid = grid::create_grid(level);
+        self.nav = NavGraph::build(&self.grid);
+        self.currents = level.currents;
+        for (player, spawn) in self.players.iter_mut().zip(self.spawns) {
+            if !player.is_out() {
+                player.respawn(spawn);
+                player.invulnerable_timer = 0.0;
+            }
+        }
+        self.enemies = enemy::create_enemies(
+            level,
+            &self.data.enemy_types,
+            self.difficulty,
+            &self.data.clips
+        );
+        self.bullets.clear();
+        self.bubbles.clear();
+        self.enemy_bullets.clear();
+        self.items.clear();
+    }
+
+    /// Swaps in a changed layout of the level being played. Everything in it
+    /// stays where it is, so level designers can see their edits right away.
+    pub fn reload_layout(&mut self, level: &Level) {
+        self.spawns = level.player_spawns.map(utils::cell_to_pos);
+        self.grid = grid::create_grid(level);
+        self.nav = NavGraph::build(&self.grid);
+        self.currents = level.currents;
+    }
+
+    /// Swaps in reloaded game data. Enemies already around take on the tuning
+    /// of their type right away, new clips show up as animations switch.
+    pub fn reload_data(&mut self, data: GameData) {
+        let clips = &data.clips;
+        for enemy in &mut self.enemies {
+            enemy.enemy_type = data.enemy_types
+                .get(enemy.enemy_type.kind)
+                .for_difficulty(self.difficulty);
+        }
+        // The old clips may name files that aren't in the new manifest
+        let animators = self.players
+            .iter_mut()
+            .map(|player| &mut player.animator)
+            .chain(self.enemies.iter_mut().map(|enemy| &mut enemy.animator))
+            .chain(
+                self.bubbles.iter_mut().flat_map(|bubble| {
+                    let occupant = bubble.occupant.as_mut().map(|enemy| &mut enemy.animator);
+                    std::iter::once(&mut bubble.animator).chain(occupant)
+                })
+            )
+            .chain(self.items.iter_mut().map(|item| &mut item.animator))
+            .chain(self.enemy_bullets.iter_mut().map(|bullet| &mut bullet.animator));
+        animators.for_each(|animator| animator.reload(clips));
+        self.data = data;
+    }
+
+    /// Points of all players together.
+    pub fn score(&self) -> i32 {
+        self.players.iter().map(|player| player.score).sum()
+    }
+
+    /// A level is done once every enemy in it has been trapped and popped, and
+    /// the fruit they left has been picked up or has gone.
+    pub fn is_cleared(&self) -> bool {
+        self.enemies.is_empty() &&
+            self.bubbles.iter().all(|bubble| bubble.occupant.is_none()) &&
+            self.items.is_empty()
+    }
+
+    /// Advances the simulation by `dt` seconds, with one input per player.
+    pub fn step(&mut self, dt: f32, inputs: &[Input]) {
+        self.events.clear();
+        if self.game_over {
+            return;
+        }
+        for (index, input) in inputs.iter().enumerate().take(self.players.len()) {
+            self.apply_input(index, input);
+        }
+        utils::update_objects(self, dt);
+        self.handle_collisions();
+        self.tick += 1;
+    }
+
+    fn apply_input(&mut self, index: usize, input: &Input) {
+        let player = &mut self.players[index];
+        if player.is_dying() || player.is_out() {
+            return;
+        }
+        if input.left && !input.right {
+            player.velocity.0 = -12.0;
+            player.view_right = false;
+        } else if input.right && !input.left {
+            player.velocity.0 = 12.0;
+            player.view_right = true;
+        } else {
+            player.velocity.0 = 0.0;
+        }
+
+        if input.jump && player.velocity.1 == 0.0 {
+            player.velocity.1 = -25.0;
+            self.events.push(Event::Jump);
+        }
+
+        if input.shoot {
+            // Bullet velocity based on player facing direction
+            let velocity = if player.view_right {
+                (20.0, 0.0) // Bullet moves right
+            } else {
+                (-20.0, 0.0) // Bullet moves left
+            };
+
+            self.bullets.push(
+                bullet::Bullet::new((player.pos.0, player.pos.1 - 1.1), velocity)
+            );
+            self.events.push(Event::Shoot);
+        }
+    }
+
+    pub fn handle_collisions(&mut self) {
+        // collision Player and Enemy
+        for enemy in &self.enemies {
+            for player in &mut self.players {
+                if
+                    (player.pos.0 - enemy.pos.0).abs() < 1.0 &&
+                    (player.pos.1 - enemy.pos.1).abs() < 1.0 &&
+                    player.hit()
+                {
+                    self.events.push(Event::Death);
+                }
+            }
+        }
+
+        // collision Bullets and Enemy: the enemy gets trapped in a bubble
+        let mut trapped = vec![];
+        self.bullets.retain(|bullet| {
+            let hit = self.enemies.iter().position(|enemy| {
+                (bullet.pos.0 - enemy.pos.0).abs() < 1.0 &&
+                    (bullet.pos.1 - enemy.pos.1).abs() < 2.0
+            });
+            if let Some(index) = hit {
+                trapped.push(self.enemies.remove(index));
+            }
+            hit.is_none()
+        });
+        for enemy in trapped {
+            self.events.push(Event::Capture);
+            let center = (enemy.pos.0, enemy.pos.1 - 1.0);
+            self.bubbles.push(bubble::Bubble::new(center, Some(enemy), &self.data.clips));
+        }
+
+        // collision Player and Bubbles: empty bubbles carry a player landing on
+        // them from above, any other touch pops them and trapped enemies turn into fruit
+        for bubble in &mut self.bubbles {
+            for player in self.players.iter_mut().filter(|player| player.is_active()) {
+                if bubble.is_popping() {
+                    break;
+                }
+                let radius = bubble::BUBBLE_RADIUS;
+                let top = bubble.pos.1 - radius;
+                let player_center = (player.pos.0, player.pos.1 - 1.2);
+                let landing =
+                    bubble.occupant.is_none() &&
+                    player.velocity.1 >= 0.0 &&
+                    (bubble.pos.0 - player.pos.0).abs() < radius &&
+                    player.pos.1 > top - 0.3 &&
+                    player.pos.1 < top + 0.6;
+                let touching =
+                    (bubble.pos.0 - player_center.0).abs() < 1.6 &&
+                    (bubble.pos.1 - player_center.1).abs() < 1.6;
+                if landing {
+                    player.pos.1 = top;
+                    player.velocity.1 = 0.0;
+                } else if touching {
+                    self.events.push(Event::Pop);
+                    if bubble.pop(&self.data.clips).is_some() {
+                        // Popping trapped enemies in quick succession drops bigger fruit
+                        self.combo = if self.combo_timer > 0.0 { self.combo + 1 } else { 0 };
+                        self.combo_timer = COMBO_WINDOW;
+                        let drop_pos = (bubble.pos.0, bubble.pos.1 + 1.0);
+                        self.items.push(item::Item::new(drop_pos, self.combo, &self.data));
+                    }
+                }
+            }
+        }
+
+        // collision Player and Items: whoever touches fruit first scores it
+        self.items.retain(|item| {
+            let collector = self.players.iter_mut().find(|player| {
+                player.is_active() &&
+                    (item.pos.0 - player.pos.0).abs() < 1.0 &&
+                    (item.pos.1 - player.pos.1).abs() < 1.5
+            });
+            match collector {
+                Some(player) => {
+                    player.add_score(item.value());
+                    self.events.push(Event::Fruit);
+                    false
+                }
+                None => true,
+            }
+        });
+
+        //collision enemy_bullets and player
+        for bullet in &self.enemy_bullets {
+            for player in &mut self.players {
+                if
+                    (bullet.pos.0 - player.pos.0).abs() < 1.0 &&
+                    (bullet.pos.1 - player.pos.1).abs() < 1.0 &&
+                    player.hit()
+                {
+                    self.events.push(Event::Death);
+                }
+            }
+        }
+
+        //collision enemy_bullet and player_bullet
+        self.bullets.retain(|bullet| {
+            let mut hit_bullet = false;
+            self.enemy_bullets.retain(|enemy_bullets| {
+                let collision =
+                    (bullet.pos.0 - enemy_bullets.pos.0).abs() < 1.0 &&
+                    (bullet.pos.1 - enemy_bullets.pos.1).abs() < 2.0;
+                if collision {
+                    hit_bullet = true;
+                }
+                !collision
+            });
+            !hit_bullet
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A closed room with a floor, and `markers` placed as `(x, y, cell)`.
+    fn room(markers: &[(usize, usize, char)]) -> Level {
+        let mut rows = vec![vec!['.'; GRID_WIDTH]; GRID_HEIGHT];
+        for (y, row) in rows.iter_mut().enumerate() {
+            row[0] = '#';
+            row[GRID_WIDTH - 1] = '#';
+            if y == 0 || y == GRID_HEIGHT - 1 {
+                row.fill('#');
+            }
+        }
+        for &(x, y, cell) in markers {
+            rows[y][x] = cell;
+        }
+        let text: Vec<String> = rows.iter().map(|row| row.iter().collect()).collect();
+        Level::parse(1, &text.join("\n")).unwrap()
+    }
+
+    fn world(level: &Level) -> World {
+        World::new(7, Difficulty::Normal, 1, level, GameData::load().unwrap())
+    }
+
+    /// Steps until `done` holds, at most `ticks` times, with no keys held.
+    fn step_until(world: &mut World, ticks: usize, done: impl Fn(&World) -> bool) -> bool {
+        for _ in 0..ticks {
+            world.step(FIXED_DT, &[Input::default()]);
+            if done(world) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Traps the only enemy of a room by shooting it from a few cells away.
+    fn trap_enemy(world: &mut World) {
+        let shoot = Input { shoot: true, ..Input::default() };
+        world.step(FIXED_DT, &[shoot]);
+        assert!(world.events.contains(&Event::Shoot));
+        let trapped = step_until(world, 60, |world| world.enemies.is_empty());
+        assert!(trapped, "the shot missed");
+        assert!(world.events.contains(&Event::Capture));
+        assert_eq!(world.bubbles.len(), 1);
+        assert!(world.bubbles[0].occupant.is_some());
+    }
+
+    #[test]
+    fn a_shot_traps_an_enemy_in_a_bubble() {
+        let mut world = world(&room(&[(4, 16, '@'), (9, 16, 'a')]));
+        assert_eq!(world.enemies.len(), 1);
+        trap_enemy(&mut world);
+        assert!(!world.is_cleared());
+    }
+
+    #[test]
+    fn popping_a_trapped_enemy_drops_fruit_that_scores() {
+        let mut world = world(&room(&[(4, 16, '@'), (9, 16, 'a')]));
+        trap_enemy(&mut world);
+
+        // Touched from the side, so the fruit drops next to the player
+        let player = &world.players[0];
+        world.bubbles[0].pos = (player.pos.0 + 1.5, player.pos.1 - 1.2);
+        world.step(FIXED_DT, &[Input::default()]);
+        assert!(world.events.contains(&Event::Pop));
+        assert_eq!(world.items.len(), 1);
+        assert!(!world.is_cleared(), "the fruit must be left to pick up");
+
+        let value = world.items[0].value();
+        world.items[0].pos = world.players[0].pos;
+        world.step(FIXED_DT, &[Input::default()]);
+        assert!(world.events.contains(&Event::Fruit));
+        assert!(world.items.is_empty());
+        assert_eq!(world.score(), value);
+        assert!(value > 0);
+        assert!(world.is_cleared());
+    }
+
+    #[test]
+    fn touching_an_enemy_costs_a_life() {
+        let mut world = world(&room(&[(4, 16, '@'), (20, 16, 'a')]));
+        let lives = world.players[0].lives;
+        world.enemies[0].pos = world.players[0].pos;
+        world.step(FIXED_DT, &[Input::default()]);
+        assert!(world.events.contains(&Event::Death));
+        assert_eq!(world.players[0].lives, lives - 1);
+        assert!(world.players[0].is_dying());
+
+        // Dying players can't be hit again
+        world.enemies[0].pos = world.players[0].pos;
+        world.step(FIXED_DT, &[Input::default()]);
+        assert_eq!(world.players[0].lives, lives - 1);
+    }
+
+    #[test]
+    fn reloaded_data_repoints_every_animator() {
+        use crate::animation::{ Animator, Clips, ANIMATIONS_PATH };
+        use crate::assets::{ AssetManifest, MANIFEST_PATH };
+
+        // One of everything that animates
+        let mut world = world(&room(&[(4, 16, '@'), (20, 16, 'a'), (24, 16, 'a')]));
+        let clips = &world.data.clips;
+        let occupant = world.enemies.remove(1);
+        world.bubbles.push(bubble::Bubble::new((10.0, 6.0), Some(occupant), clips));
+        world.bubbles.push(bubble::Bubble::new((14.0, 6.0), None, clips));
+        world.items.push(item::Item::new((12.0, 10.0), 0, &world.data));
+        world.enemy_bullets.push(enemy_bullet::EnemyBullet {
+            pos: (16.0, 10.0),
+            velocity: (0.0, 0.0),
+            animator: Animator::new(clips.get("projectile.bolt")),
+        });
+        world.step(FIXED_DT, &[Input::default()]);
+
+        // Every image file renamed, as if the art had been swapped
+        let manifest_text = std::fs::read_to_string(MANIFEST_PATH).unwrap();
+        let manifest = AssetManifest::parse(&manifest_text.replace(".png", "_v2.png")).unwrap();
+        let animations = std::fs::read_to_string(ANIMATIONS_PATH).unwrap();
+        let data = GameData {
+            clips: Clips::parse(&animations, &manifest).unwrap(),
+            manifest,
+            ..world.data.clone()
+        };
+        world.reload_data(data);
+
+        let files: Vec<&String> = world.data.manifest
+            .assets()
+            .iter()
+            .flat_map(|asset| &asset.files)
+            .collect();
+        let occupants = world.bubbles.iter().filter_map(|bubble| bubble.occupant.as_ref());
+        let animators: Vec<&Animator> = world.players
+            .iter()
+            .map(|player| &player.animator)
+            .chain(world.enemies.iter().map(|enemy| &enemy.animator))
+            .chain(occupants.map(|enemy| &enemy.animator))
+            .chain(world.bubbles.iter().map(|bubble| &bubble.animator))
+            .chain(world.items.iter().map(|item| &item.animator))
+            .chain(world.enemy_bullets.iter().map(|bullet| &bullet.animator))
+            .collect();
+        assert!(animators.len() >= 6);
+        for animator in animators {
+            assert!(files.iter().any(|file| *file == animator.frame()), "{}", animator.frame());
+        }
+    }
+
+}